@@ -13,16 +13,16 @@ use ahash::AHashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    core::{set::SetError, RequestParams},
+    core::{set::SetError, AccountId, RequestParams},
     Error,
 };
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CopyBlobRequest {
     #[serde(rename = "fromAccountId")]
-    from_account_id: String,
+    from_account_id: AccountId,
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
     #[serde(rename = "blobIds")]
     blob_ids: Vec<String>,
 }
@@ -40,7 +40,7 @@ pub struct CopyBlobResponse {
 }
 
 impl CopyBlobRequest {
-    pub fn new(params: RequestParams, from_account_id: impl Into<String>) -> Self {
+    pub fn new(params: RequestParams, from_account_id: impl Into<AccountId>) -> Self {
         CopyBlobRequest {
             from_account_id: from_account_id.into(),
             account_id: params.account_id,
@@ -9,51 +9,87 @@
  * except according to those terms.
  */
 
+use bytes::Bytes;
 use reqwest::header::CONTENT_TYPE;
 
-use crate::{client::Client, core::session::URLPart};
-
-#[cfg(feature = "blocking")]
-use reqwest::blocking::Client as HttpClient;
-#[cfg(feature = "async")]
-use reqwest::Client as HttpClient;
+use crate::{
+    client::Client,
+    core::session::{push_url_value, URLPart},
+};
 
 impl Client {
+    /// Downloads a blob. Dropping the future aborts the download, see
+    /// [`Client::send`].
     #[maybe_async::maybe_async]
     pub async fn download(&self, blob_id: &str) -> crate::Result<Vec<u8>> {
+        self.download_as(blob_id, None, None).await
+    }
+
+    /// Builds the download URL of a blob. The `{name}` and `{type}` template
+    /// variables are filled with `name` and `content_type`, which default to
+    /// `none` and `application/octet-stream`. All the variables are
+    /// percent-encoded. The server serves the blob with
+    /// the given type, regardless of the type stored with it, so passing
+    /// `application/octet-stream` makes browsers download it instead of
+    /// rendering it inline.
+    pub fn blob_download_url(
+        &self,
+        blob_id: &str,
+        name: Option<&str>,
+        content_type: Option<&str>,
+    ) -> String {
         let account_id = self.default_account_id();
         let mut download_url = String::with_capacity(
             self.session().download_url().len() + account_id.len() + blob_id.len(),
         );
 
-        for part in self.download_url() {
+        for part in self.download_url().iter() {
             match part {
                 URLPart::Value(value) => {
                     download_url.push_str(value);
                 }
                 URLPart::Parameter(param) => match param {
                     super::URLParameter::AccountId => {
-                        download_url.push_str(account_id);
+                        push_url_value(&mut download_url, account_id);
                     }
                     super::URLParameter::BlobId => {
-                        download_url.push_str(blob_id);
+                        push_url_value(&mut download_url, blob_id);
                     }
                     super::URLParameter::Name => {
-                        download_url.push_str("none");
+                        push_url_value(&mut download_url, name.unwrap_or("none"));
                     }
                     super::URLParameter::Type => {
-                        download_url.push_str("application/octet-stream");
+                        push_url_value(
+                            &mut download_url,
+                            content_type.unwrap_or("application/octet-stream"),
+                        );
                     }
                 },
             }
         }
+        download_url
+    }
+
+    /// Same as [`Client::download`], requesting the blob with the given name
+    /// and content type, see [`Client::blob_download_url`].
+    ///
+    /// As with uploads, the credentials are sent to the `downloadUrl` of the
+    /// session even when it points to a different host than the API.
+    #[maybe_async::maybe_async]
+    pub async fn download_as(
+        &self,
+        blob_id: &str,
+        name: Option<&str>,
+        content_type: Option<&str>,
+    ) -> crate::Result<Vec<u8>> {
+        let download_url = self.blob_download_url(blob_id, name, content_type);
 
+        let _permit = self.acquire_download().await;
         let mut headers = self.headers().clone();
         headers.remove(CONTENT_TYPE);
 
         Client::handle_error(
-            HttpClient::builder()
-                .timeout(self.timeout())
+            self.http_builder()
                 .danger_accept_invalid_certs(self.accept_invalid_certs)
                 .redirect(self.redirect_policy())
                 .default_headers(headers)
@@ -68,4 +104,197 @@ impl Client {
         .map(|bytes| bytes.to_vec())
         .map_err(|err| err.into())
     }
+
+    /// Downloads several blobs, each requested with the given content type.
+    /// The downloads run concurrently, up to the limit set by
+    /// [`Client::set_max_concurrent_downloads`]. The results are returned in
+    /// the order of `blobs`, so that a failed download does not affect the
+    /// others.
+    #[cfg(feature = "async")]
+    pub async fn download_blobs(&self, blobs: &[(&str, &str)]) -> Vec<crate::Result<Bytes>> {
+        futures_util::future::join_all(blobs.iter().map(|(blob_id, content_type)| async move {
+            self.download_as(blob_id, None, Some(content_type))
+                .await
+                .map(Bytes::from)
+        }))
+        .await
+    }
+
+    /// Downloads several blobs, each requested with the given content type.
+    /// The results are returned in the order of `blobs`, so that a failed
+    /// download does not affect the others.
+    #[cfg(feature = "blocking")]
+    pub fn download_blobs(&self, blobs: &[(&str, &str)]) -> Vec<crate::Result<Bytes>> {
+        blobs
+            .iter()
+            .map(|(blob_id, content_type)| {
+                self.download_as(blob_id, None, Some(content_type))
+                    .map(Bytes::from)
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use std::time::Duration;
+
+    use crate::test_server::{block_on, TestResponse, TestServer};
+
+    #[test]
+    fn download_type_override() {
+        let server = TestServer::start(|request| {
+            assert!(request.path.starts_with("/download/"));
+            TestResponse::new(200, "hello")
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            assert_eq!(
+                client.blob_download_url("b1", None, None),
+                format!(
+                    "{}/download/a/b1/none?accept=application%2Foctet-stream",
+                    server.url
+                )
+            );
+            assert_eq!(
+                client.blob_download_url("b1", Some("photo.jpg"), Some("image/jpeg")),
+                format!("{}/download/a/b1/photo.jpg?accept=image%2Fjpeg", server.url)
+            );
+            assert_eq!(
+                client.blob_download_url("b1", Some("report #1/a b.pdf"), None),
+                format!(
+                    "{}/download/a/b1/report%20%231%2Fa%20b.pdf?accept=application%2Foctet-stream",
+                    server.url
+                )
+            );
+            assert_eq!(
+                client
+                    .download_as("b2", Some("note.txt"), Some("text/plain"))
+                    .await
+                    .unwrap(),
+                b"hello"
+            );
+        });
+        assert_eq!(
+            server.requests()[0].path,
+            "/download/a/b2/note.txt?accept=text%2Fplain"
+        );
+    }
+
+    #[test]
+    fn download_limit_independent_of_uploads() {
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:core"]["maxConcurrentUpload"] =
+                    1.into()
+            },
+            |_| TestResponse::new(200, "blob"),
+        );
+
+        block_on(async {
+            let client = server.connect().await;
+            assert_eq!(client.max_concurrent_uploads(), 1);
+            assert_eq!(client.max_concurrent_downloads(), 4);
+
+            let _upload = client.acquire_upload().await;
+            let results = tokio::time::timeout(
+                Duration::from_secs(5),
+                client.download_blobs(&[("b1", "text/plain"), ("b2", "text/plain")]),
+            )
+            .await
+            .unwrap();
+            assert!(results.iter().all(|result| result.is_ok()));
+        });
+    }
+
+    #[test]
+    fn download_waits_for_permit() {
+        let server = TestServer::start(|_| TestResponse::new(200, "blob"));
+
+        block_on(async {
+            let mut client = server.connect().await;
+            client.set_max_concurrent_downloads(1);
+
+            let permit = client.acquire_download().await;
+            assert!(
+                tokio::time::timeout(Duration::from_millis(100), client.download("b1"))
+                    .await
+                    .is_err()
+            );
+            drop(permit);
+            assert_eq!(client.download("b1").await.unwrap(), b"blob");
+        });
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn download_blobs_partial_failure() {
+        let server = TestServer::start(|request| match request.path.split('/').nth(3).unwrap() {
+            "missing" => TestResponse::new(404, ""),
+            blob_id => TestResponse::new(200, blob_id.to_uppercase()),
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let results = client
+                .download_blobs(&[
+                    ("b1", "image/png"),
+                    ("missing", "image/png"),
+                    ("b3", "image/gif"),
+                ])
+                .await;
+            assert_eq!(results.len(), 3);
+            assert_eq!(results[0].as_ref().unwrap().as_ref(), b"B1");
+            assert!(matches!(
+                results[1],
+                Err(crate::Error::Server { status: 404, .. })
+            ));
+            assert_eq!(results[2].as_ref().unwrap().as_ref(), b"B3");
+        });
+
+        let mut paths = server
+            .requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "/download/a/b1/none?accept=image%2Fpng",
+                "/download/a/b3/none?accept=image%2Fgif",
+                "/download/a/missing/none?accept=image%2Fpng"
+            ]
+        );
+    }
+
+    #[test]
+    fn drop_cancels_download() {
+        let server = TestServer::start(|request| {
+            if request.path.starts_with("/download/") {
+                TestResponse::new(200, "")
+                    .chunked(vec![vec![b'a'; 1024]; 20], Duration::from_millis(50))
+            } else {
+                TestResponse::json(
+                    200,
+                    serde_json::json!({"sessionState": "s1", "methodResponses": []}).to_string(),
+                )
+            }
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            assert!(
+                tokio::time::timeout(Duration::from_millis(100), client.download("b1"))
+                    .await
+                    .is_err()
+            );
+            assert!(server.wait_aborted(Duration::from_secs(5)).await);
+
+            // The client can still be used after cancelling.
+            client.build().send().await.unwrap();
+        });
+        assert_eq!(server.requests().len(), 2);
+    }
 }
@@ -11,7 +11,10 @@
 
 use crate::{
     client::Client,
-    core::request::{Arguments, Request},
+    core::{
+        request::{Arguments, Request},
+        AccountId,
+    },
     Method,
 };
 
@@ -21,7 +24,7 @@ impl Client {
     #[maybe_async::maybe_async]
     pub async fn blob_copy(
         &self,
-        from_account_id: impl Into<String>,
+        from_account_id: impl Into<AccountId>,
         blob_id: impl Into<String>,
     ) -> crate::Result<String> {
         let blob_id = blob_id.into();
@@ -36,7 +39,7 @@ impl Client {
 
 impl Request<'_> {
     #[maybe_async::maybe_async]
-    pub fn copy_blob(&mut self, from_account_id: impl Into<String>) -> &mut CopyBlobRequest {
+    pub fn copy_blob(&mut self, from_account_id: impl Into<AccountId>) -> &mut CopyBlobRequest {
         self.add_method_call(
             Method::CopyBlob,
             Arguments::blob_copy(self.params(Method::CopyBlob), from_account_id.into()),
@@ -9,15 +9,24 @@
  * except according to those terms.
  */
 
-use reqwest::header::CONTENT_TYPE;
-use serde::Deserialize;
-
-use crate::{client::Client, core::session::URLPart};
+use std::path::Path;
 
 #[cfg(feature = "blocking")]
-use reqwest::blocking::Client as HttpClient;
+use reqwest::blocking::Body;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
 #[cfg(feature = "async")]
-use reqwest::Client as HttpClient;
+use reqwest::Body;
+use serde::Deserialize;
+
+use crate::{
+    client::Client,
+    core::{
+        error::{JMAPError, ProblemType},
+        session::{push_url_value, URLPart},
+        AccountId,
+    },
+    Error,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct UploadResponse {
@@ -35,52 +44,161 @@ pub struct UploadResponse {
 }
 
 impl Client {
+    /// Uploads a blob. Dropping the future aborts the upload, see
+    /// [`Client::send`]. At most [`Client::max_concurrent_uploads`] uploads
+    /// run at the same time, any others wait for their turn. Rate limited
+    /// uploads are retried as configured by [`Client::set_rate_limit_retries`].
+    ///
+    /// The credentials are sent to the `uploadUrl` of the session even when
+    /// it points to a different host than the API, so the session should
+    /// only be fetched from a trusted server.
     #[maybe_async::maybe_async]
     pub async fn upload(
         &self,
-        account_id: Option<&str>,
+        account_id: Option<&AccountId>,
         blob: Vec<u8>,
         content_type: Option<&str>,
+    ) -> crate::Result<UploadResponse> {
+        self.upload_body(account_id, blob.into(), None, content_type)
+            .await
+    }
+
+    #[maybe_async::maybe_async]
+    async fn upload_body(
+        &self,
+        account_id: Option<&AccountId>,
+        body: Body,
+        content_length: Option<u64>,
+        content_type: Option<&str>,
     ) -> crate::Result<UploadResponse> {
         let account_id = account_id.unwrap_or_else(|| self.default_account_id());
         let mut upload_url =
             String::with_capacity(self.session().upload_url().len() + account_id.len());
 
-        for part in self.upload_url() {
+        for part in self.upload_url().iter() {
             match part {
                 URLPart::Value(value) => {
                     upload_url.push_str(value);
                 }
                 URLPart::Parameter(param) => {
                     if let super::URLParameter::AccountId = param {
-                        upload_url.push_str(account_id);
+                        push_url_value(&mut upload_url, account_id);
                     }
                 }
             }
         }
 
-        serde_json::from_slice::<UploadResponse>(
-            &Client::handle_error(
-                HttpClient::builder()
-                    .timeout(self.timeout())
-                    .danger_accept_invalid_certs(self.accept_invalid_certs)
-                    .redirect(self.redirect_policy())
-                    .default_headers(self.headers().clone())
-                    .build()?
-                    .post(upload_url)
-                    .header(
-                        CONTENT_TYPE,
-                        content_type.unwrap_or("application/octet-stream"),
-                    )
-                    .body(blob)
-                    .send()
-                    .await?,
+        let _permit = self.acquire_upload().await;
+        let mut request = self
+            .http_builder()
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .redirect(self.redirect_policy())
+            .default_headers(self.headers().clone())
+            .build()?
+            .post(upload_url)
+            .header(
+                CONTENT_TYPE,
+                content_type.unwrap_or("application/octet-stream"),
+            );
+        if let Some(content_length) = content_length {
+            request = request.header(CONTENT_LENGTH, content_length);
+        }
+        let response = self.send_retrying(request.body(body)).await?;
+        let response = match Client::handle_error(response).await {
+            Ok(response) => response,
+            Err(Error::Problem(problem))
+                if matches!(problem.error(), ProblemType::JMAP(JMAPError::Limit))
+                    && problem.limit() == Some("maxSizeUpload") =>
+            {
+                return Err(Error::BlobTooLarge(
+                    self.session()
+                        .core_capabilities()
+                        .map(|capabilities| capabilities.max_size_upload())
+                        .unwrap_or_default(),
+                ));
+            }
+            Err(err) => return Err(err),
+        };
+
+        serde_json::from_slice::<UploadResponse>(&response.bytes().await?).map_err(|err| err.into())
+    }
+
+    /// Uploads the contents of the file at `path`, inferring its content type
+    /// from the file extension. The file is streamed to the server rather than
+    /// read into memory, so rate limited uploads are not retried.
+    #[maybe_async::maybe_async]
+    pub async fn upload_file(
+        &self,
+        account_id: Option<&AccountId>,
+        path: impl AsRef<Path>,
+    ) -> crate::Result<UploadResponse> {
+        let path = path.as_ref();
+        let (body, content_length) = file_body(path).await.map_err(|err| {
+            std::io::Error::new(
+                err.kind(),
+                format!("Failed to read {}: {}", path.display(), err),
             )
-            .await?
-            .bytes()
-            .await?,
+        })?;
+        self.upload_body(
+            account_id,
+            body,
+            Some(content_length),
+            content_type_from_path(path).into(),
         )
-        .map_err(|err| err.into())
+        .await
+    }
+}
+
+#[cfg(feature = "async")]
+async fn file_body(path: &Path) -> std::io::Result<(Body, u64)> {
+    use tokio::io::AsyncReadExt;
+
+    let file = tokio::fs::File::open(path).await?;
+    let content_length = file.metadata().await?.len();
+    let stream = futures_util::stream::try_unfold(file, |mut file| async move {
+        let mut chunk = vec![0; 64 * 1024];
+        let len = file.read(&mut chunk).await?;
+        chunk.truncate(len);
+        Ok::<_, std::io::Error>((len > 0).then_some((chunk, file)))
+    });
+    Ok((Body::wrap_stream(stream), content_length))
+}
+
+#[cfg(feature = "blocking")]
+fn file_body(path: &Path) -> std::io::Result<(Body, u64)> {
+    let file = std::fs::File::open(path)?;
+    let content_length = file.metadata()?.len();
+    Ok((Body::new(file), content_length))
+}
+
+fn content_type_from_path(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "eml" => "message/rfc822",
+        "txt" => "text/plain",
+        "htm" | "html" => "text/html",
+        "csv" => "text/csv",
+        "ics" => "text/calendar",
+        "vcf" => "text/vcard",
+        "sieve" | "siv" => "application/sieve",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
     }
 }
 
@@ -105,3 +223,240 @@ impl UploadResponse {
         std::mem::take(&mut self.blob_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::content_type_from_path;
+
+    #[test]
+    fn infer_content_type() {
+        assert_eq!(content_type_from_path(Path::new("x.EML")), "message/rfc822");
+        assert_eq!(content_type_from_path(Path::new("a.png")), "image/png");
+        assert_eq!(
+            content_type_from_path(Path::new("no_extension")),
+            "application/octet-stream"
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn upload_concurrency_limit() {
+        use std::{
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+            time::Duration,
+        };
+
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let in_flight_ = in_flight.clone();
+        let max_in_flight_ = max_in_flight.clone();
+        let server = TestServer::start_threaded(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:core"]["maxConcurrentUpload"] =
+                    2.into()
+            },
+            move |request| {
+                let current = in_flight_.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight_.fetch_max(current, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(100));
+                in_flight_.fetch_sub(1, Ordering::SeqCst);
+                TestResponse::json(
+                    200,
+                    serde_json::json!({
+                        "accountId": "a",
+                        "blobId": format!("b{}", request.body.len()),
+                        "type": "application/octet-stream",
+                        "size": request.body.len()
+                    })
+                    .to_string(),
+                )
+            },
+        );
+
+        block_on(async {
+            let client = server.connect().await;
+            assert_eq!(client.max_concurrent_uploads(), 2);
+            let uploads = futures_util::future::join_all(
+                (1..=5).map(|len| client.upload(None, vec![0; len], None)),
+            )
+            .await;
+            assert!(uploads.iter().all(|upload| upload.is_ok()));
+        });
+        assert_eq!(server.requests().len(), 5);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn upload_problems() {
+        use crate::{
+            test_server::{block_on, TestResponse, TestServer},
+            Error,
+        };
+
+        let server = TestServer::start(|request| {
+            let (status, problem) = if request.body.len() > 3 {
+                (
+                    400,
+                    r#"{"type": "urn:ietf:params:jmap:error:limit", "status": 400, "limit": "maxSizeUpload"}"#,
+                )
+            } else {
+                (
+                    403,
+                    r#"{"type": "urn:example:overQuota", "status": 403, "title": "Over quota"}"#,
+                )
+            };
+            TestResponse::new(status, problem).header("Content-Type", "application/problem+json")
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let err = client
+                .upload(None, b"abc".to_vec(), None)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Request failed: urn:example:overQuota (status 403): Over quota"
+            );
+            assert!(matches!(
+                client.upload(None, b"abcdef".to_vec(), None).await,
+                Err(Error::BlobTooLarge(50000000))
+            ));
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn upload_to_other_host() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start_with_session(
+            |session| {
+                let url = session["apiUrl"]
+                    .as_str()
+                    .unwrap()
+                    .replace("127.0.0.1", "localhost");
+                session["uploadUrl"] = url.replace("/api", "/upload/{accountId}/").into();
+                session["downloadUrl"] = url
+                    .replace(
+                        "/api",
+                        "/download/{accountId}/{blobId}/{name}?accept={type}",
+                    )
+                    .into();
+            },
+            |request| {
+                TestResponse::json(
+                    201,
+                    serde_json::json!({
+                        "accountId": "a",
+                        "blobId": "b1",
+                        "type": "text/plain",
+                        "size": request.body.len()
+                    })
+                    .to_string(),
+                )
+            },
+        );
+
+        block_on(async {
+            let client = server.connect().await;
+            assert!(client
+                .session()
+                .upload_url()
+                .starts_with("http://localhost:"));
+            client
+                .upload(None, b"hello".to_vec(), Some("text/plain"))
+                .await
+                .unwrap();
+            client.download("b1").await.unwrap();
+        });
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[1].method, "GET");
+        for request in requests {
+            assert!(request.header("Host").unwrap().starts_with("localhost:"));
+            assert_eq!(request.header("Authorization"), Some("Bearer token"));
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn upload_file() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|request| {
+            TestResponse::json(
+                201,
+                serde_json::json!({
+                    "accountId": "a",
+                    "blobId": "b1",
+                    "type": request.header("Content-Type").unwrap(),
+                    "size": request.body.len()
+                })
+                .to_string(),
+            )
+        });
+        let path = std::env::temp_dir().join("jmap-client-upload-file-test.txt");
+        let contents = (0..100_000).map(|pos| pos as u8).collect::<Vec<_>>();
+        std::fs::write(&path, &contents).unwrap();
+
+        let upload = block_on(async {
+            let client = server.connect().await;
+            client.upload_file(None, &path).await
+        });
+        std::fs::remove_file(&path).unwrap();
+        let upload = upload.unwrap();
+        assert_eq!(upload.content_type(), "text/plain");
+        assert_eq!(upload.size(), contents.len());
+        assert_eq!(server.requests()[0].body, contents);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn upload_rate_limited() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let calls = AtomicUsize::new(0);
+        let server = TestServer::start(move |request| {
+            if calls.fetch_add(1, Ordering::Relaxed) == 0 {
+                TestResponse::new(429, "").header("Retry-After", "0")
+            } else {
+                TestResponse::json(
+                    201,
+                    serde_json::json!({
+                        "accountId": "a",
+                        "blobId": "b1",
+                        "type": "text/plain",
+                        "size": request.body.len()
+                    })
+                    .to_string(),
+                )
+            }
+        });
+
+        block_on(async {
+            let mut client = server.connect().await;
+            client.set_rate_limit_retries(1);
+            let upload = client
+                .upload(None, b"hello".to_vec(), Some("text/plain"))
+                .await
+                .unwrap();
+            assert_eq!(upload.blob_id(), "b1");
+        });
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| request.body == b"hello"));
+    }
+}
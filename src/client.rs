@@ -10,60 +10,81 @@
  */
 
 use std::{
+    io::Write,
     net::IpAddr,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use ahash::AHashSet;
+use bytes::Bytes;
+use flate2::{write::GzEncoder, Compression};
 #[cfg(feature = "blocking")]
-use reqwest::blocking::{Client as HttpClient, Response};
+use reqwest::blocking::{
+    Client as HttpClient, ClientBuilder as HttpClientBuilder, RequestBuilder, Response,
+};
 use reqwest::{
     header::{self},
-    redirect,
+    redirect, StatusCode,
 };
 #[cfg(feature = "async")]
-use reqwest::{Client as HttpClient, Response};
+use reqwest::{Client as HttpClient, ClientBuilder as HttpClientBuilder, RequestBuilder, Response};
 
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
     blob,
     core::{
-        request::{self, Request},
+        changes::SyncState,
+        request::Request,
         response,
         session::{Session, URLPart},
+        AccountId,
     },
-    Error,
+    Error, URI,
 };
 
 const DEFAULT_TIMEOUT_MS: u64 = 10 * 1000;
+const COMPRESSION_THRESHOLD: usize = 1024;
+const DEFAULT_BODY_UPLOAD_THRESHOLD: usize = 1024 * 1024;
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
 static USER_AGENT: &str = concat!("jmap-client/", env!("CARGO_PKG_VERSION"));
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Credentials {
     Basic(String),
     Bearer(String),
 }
 
 pub struct Client {
-    session: parking_lot::Mutex<Arc<Session>>,
+    session: parking_lot::Mutex<ClientSession>,
     session_url: String,
-    api_url: String,
     session_updated: AtomicBool,
+    session_etag: parking_lot::Mutex<Option<String>>,
+    request_compression: bool,
+    compression_unsupported: AtomicBool,
+    validate_sort_options: bool,
+    strict_responses: bool,
+    describe_references: bool,
+    rate_limit_retries: u32,
+    circuit_breaker: Option<CircuitBreaker>,
+    trace_id: Option<TraceId>,
+    upload_limiter: TransferLimiter,
+    download_limiter: TransferLimiter,
+    body_upload_threshold: usize,
     trusted_hosts: Arc<AHashSet<String>>,
-
-    upload_url: Vec<URLPart<blob::URLParameter>>,
-    download_url: Vec<URLPart<blob::URLParameter>>,
-    #[cfg(feature = "async")]
-    event_source_url: Vec<URLPart<crate::event_source::URLParameter>>,
+    sync_state: parking_lot::Mutex<SyncState>,
 
     headers: header::HeaderMap,
-    default_account_id: String,
+    default_account_id: AccountId,
     timeout: Duration,
+    #[cfg(feature = "event-source")]
+    event_source_timeout: Duration,
+    http2: Http2Settings,
     pub(crate) accept_invalid_certs: bool,
 
     #[cfg(feature = "websockets")]
@@ -72,12 +93,27 @@ pub struct Client {
     pub(crate) ws: tokio::sync::Mutex<Option<crate::client_ws::WsStream>>,
 }
 
+#[derive(Clone)]
 pub struct ClientBuilder {
     credentials: Option<Credentials>,
     trusted_hosts: AHashSet<String>,
     forwarded_for: Option<String>,
     accept_invalid_certs: bool,
     timeout: Duration,
+    headers: header::HeaderMap,
+    user_agent: Option<String>,
+    http2: Http2Settings,
+}
+
+/// Options used by [Client::connect_with_options()](struct.Client.html#method.connect_with_options).
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// Timeout for all the requests, 10 seconds when not set.
+    pub timeout: Option<Duration>,
+    /// Additional headers sent with all the requests, including the session fetch.
+    pub headers: header::HeaderMap,
+    /// Replaces the default `User-Agent`.
+    pub user_agent: Option<String>,
 }
 
 impl Default for ClientBuilder {
@@ -97,6 +133,9 @@ impl ClientBuilder {
             timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
             forwarded_for: None,
             accept_invalid_certs: false,
+            headers: header::HeaderMap::new(),
+            user_agent: None,
+            http2: Http2Settings::default(),
         }
     }
 
@@ -140,7 +179,8 @@ impl ClientBuilder {
     ///
     /// The timeout can be changed after the `Client` has been created by using [Client.set_timeout()](struct.Client.html#method.set_timeout).
     ///
-    /// By default the timeout is 10 seconds.
+    /// By default the timeout is 10 seconds. A zero duration disables the timeout,
+    /// see [Client.set_no_timeout()](struct.Client.html#method.set_no_timeout).
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
         self
@@ -181,69 +221,132 @@ impl ClientBuilder {
         self
     }
 
-    /// Connects to the JMAP API Session URL.
-    ///
-    /// Setting up [Credentials](struct.ClientBuilder.html#method.credentials) must be done before calling this function.
+    /// Set additional headers to send with all the requests, including the
+    /// initial session fetch.
+    pub fn headers(mut self, headers: header::HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Set the `User-Agent` sent with all the requests, replacing the default one.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Uses HTTP/2 without negotiating it first, for servers that are known
+    /// to speak HTTP/2 over cleartext connections. Requests to servers that
+    /// only speak HTTP/1 will fail.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http2.prior_knowledge = true;
+        self
+    }
+
+    /// Lets HTTP/2 connections adapt their flow-control window to the
+    /// measured bandwidth, which speeds up large responses over fast links.
+    /// When enabled, the initial window sizes are ignored.
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2.adaptive_window = enabled;
+        self
+    }
+
+    /// Sets the initial HTTP/2 flow-control window of each stream, in bytes.
+    pub fn http2_initial_stream_window_size(mut self, size: u32) -> Self {
+        self.http2.initial_stream_window_size = Some(size);
+        self
+    }
+
+    /// Sets the initial HTTP/2 flow-control window of each connection, in bytes.
+    pub fn http2_initial_connection_window_size(mut self, size: u32) -> Self {
+        self.http2.initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// Fetches the JMAP API Session URL to check the credentials, without
+    /// setting up a client. The session is fetched as in
+    /// [ClientBuilder.connect()](struct.ClientBuilder.html#method.connect),
+    /// following redirects only to trusted hosts. Fails with
+    /// [`Error::Unauthorized`] when the server rejects the credentials, while
+    /// unreachable servers fail with [`Error::Transport`] and responses that
+    /// are not a JMAP session, such as captive portal pages, fail with
+    /// [`Error::Json`].
     #[maybe_async::maybe_async]
-    pub async fn connect(self, url: &str) -> crate::Result<Client> {
-        let authorization = match self.credentials.expect("Missing credentials") {
-            Credentials::Basic(s) => format!("Basic {}", s),
-            Credentials::Bearer(s) => format!("Bearer {}", s),
-        };
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::USER_AGENT,
-            header::HeaderValue::from_static(USER_AGENT),
-        );
-        headers.insert(
-            header::AUTHORIZATION,
-            header::HeaderValue::from_str(&authorization).unwrap(),
-        );
-        if let Some(forwarded_for) = self.forwarded_for {
-            headers.insert(
-                header::FORWARDED,
-                header::HeaderValue::from_str(&forwarded_for).unwrap(),
-            );
+    pub async fn check_credentials(mut self, url: &str) -> crate::Result<()> {
+        let trusted_hosts = Arc::new(std::mem::take(&mut self.trusted_hosts));
+        let headers = self.session_headers();
+        let response = self
+            .session_client(trusted_hosts, headers)?
+            .get(format!("{}/.well-known/jmap", url))
+            .send()
+            .await?;
+        if matches!(
+            response.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            return Err(Error::Unauthorized);
         }
+        serde_json::from_slice::<Session>(&Client::handle_error(response).await?.bytes().await?)?;
+        Ok(())
+    }
 
-        let trusted_hosts = Arc::new(self.trusted_hosts);
+    /// Connects to the JMAP API Session URL, making up to `attempts` attempts
+    /// while the server cannot be reached or does not answer within the
+    /// timeout, for example because the network is still coming up. The wait
+    /// between attempts starts at `delay` and doubles after each failure. Any
+    /// other error, including authentication failures, is returned immediately.
+    #[maybe_async::maybe_async]
+    pub async fn connect_with_retry(
+        self,
+        url: &str,
+        attempts: u32,
+        delay: Duration,
+    ) -> crate::Result<Client> {
+        let mut delay = delay;
+        let mut attempt = 1;
+        loop {
+            match self.clone().connect(url).await {
+                Err(Error::Transport(err))
+                    if attempt < attempts && (err.is_connect() || err.is_timeout()) =>
+                {
+                    sleep(delay).await;
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
 
-        let trusted_hosts_ = trusted_hosts.clone();
+    /// Connects to the JMAP API Session URL.
+    ///
+    /// Setting up [Credentials](struct.ClientBuilder.html#method.credentials) must be done before calling this function.
+    #[maybe_async::maybe_async]
+    pub async fn connect(mut self, url: &str) -> crate::Result<Client> {
+        let trusted_hosts = Arc::new(std::mem::take(&mut self.trusted_hosts));
+        let mut headers = self.session_headers();
+        #[cfg(feature = "websockets")]
+        let authorization = headers[header::AUTHORIZATION]
+            .to_str()
+            .unwrap_or_default()
+            .to_string();
         let session_url = format!("{}/.well-known/jmap", url);
-        let session: Session = serde_json::from_slice(
-            &Client::handle_error(
-                HttpClient::builder()
-                    .timeout(self.timeout)
-                    .danger_accept_invalid_certs(self.accept_invalid_certs)
-                    .redirect(redirect::Policy::custom(move |attempt| {
-                        if attempt.previous().len() > 5 {
-                            attempt.error("Too many redirects.")
-                        } else if matches!( attempt.url().host_str(), Some(host) if trusted_hosts_.contains(host) )
-                        {
-                                attempt.follow()
-                        } else {
-                            let message = format!(
-                                "Aborting redirect request to unknown host '{}'.",
-                                attempt.url().host_str().unwrap_or("")
-                            );
-                            attempt.error(message)
-                        }
-                    }))
-                    .default_headers(headers.clone())
-                    .build()?
-                    .get(&session_url)
-                    .send()
-                    .await?,
-            )
-            .await?
-            .bytes()
-            .await?,
-        )?;
+        let response = Client::handle_error(
+            self.session_client(trusted_hosts.clone(), headers.clone())?
+                .get(&session_url)
+                .send()
+                .await?,
+        )
+        .await?;
+        let session_etag = etag(&response);
+        let final_url = response.url().clone();
+        let mut session: Session = serde_json::from_slice(&response.bytes().await?)?;
+
+        session.resolve_urls(&final_url);
 
         let default_account_id = session
             .primary_accounts()
             .next()
-            .map(|a| a.1.to_string())
+            .map(|a| AccountId::from(a.1))
             .unwrap_or_default();
 
         headers.insert(
@@ -251,20 +354,40 @@ impl ClientBuilder {
             header::HeaderValue::from_static("application/json"),
         );
 
+        let max_concurrent_upload = session
+            .core_capabilities()
+            .map(|capabilities| capabilities.max_concurrent_upload())
+            .unwrap_or(1);
+        let max_concurrent_download = session
+            .core_capabilities()
+            .map(|capabilities| capabilities.max_concurrent_requests())
+            .unwrap_or(1);
+
         Ok(Client {
-            download_url: URLPart::parse(session.download_url())?,
-            upload_url: URLPart::parse(session.upload_url())?,
-            #[cfg(feature = "async")]
-            event_source_url: URLPart::parse(session.event_source_url())?,
-            api_url: session.api_url().to_string(),
-            session: parking_lot::Mutex::new(Arc::new(session)),
+            session: parking_lot::Mutex::new(ClientSession::new(session)?),
             session_url,
             session_updated: true.into(),
+            session_etag: session_etag.into(),
+            request_compression: false,
+            compression_unsupported: false.into(),
+            validate_sort_options: true,
+            strict_responses: false,
+            describe_references: cfg!(debug_assertions),
+            rate_limit_retries: 0,
+            circuit_breaker: None,
+            trace_id: None,
+            upload_limiter: TransferLimiter::new(max_concurrent_upload),
+            download_limiter: TransferLimiter::new(max_concurrent_download),
+            body_upload_threshold: DEFAULT_BODY_UPLOAD_THRESHOLD,
             accept_invalid_certs: self.accept_invalid_certs,
             trusted_hosts,
+            sync_state: Default::default(),
             #[cfg(feature = "websockets")]
             authorization,
             timeout: self.timeout,
+            #[cfg(feature = "event-source")]
+            event_source_timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            http2: self.http2,
             headers,
             default_account_id,
             #[cfg(feature = "websockets")]
@@ -273,17 +396,184 @@ impl ClientBuilder {
     }
 }
 
+impl ClientBuilder {
+    fn session_headers(&mut self) -> header::HeaderMap {
+        let authorization = self
+            .credentials
+            .take()
+            .expect("Missing credentials")
+            .authorization();
+        let mut headers = std::mem::take(&mut self.headers);
+        headers.insert(
+            header::USER_AGENT,
+            self.user_agent
+                .take()
+                .and_then(|user_agent| header::HeaderValue::from_str(&user_agent).ok())
+                .unwrap_or(header::HeaderValue::from_static(USER_AGENT)),
+        );
+        headers.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_str(&authorization).unwrap(),
+        );
+        if let Some(forwarded_for) = self.forwarded_for.take() {
+            headers.insert(
+                header::FORWARDED,
+                header::HeaderValue::from_str(&forwarded_for).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn session_client(
+        &self,
+        trusted_hosts: Arc<AHashSet<String>>,
+        headers: header::HeaderMap,
+    ) -> crate::Result<HttpClient> {
+        self.http2
+            .apply(with_timeout(HttpClient::builder(), self.timeout))
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() > 5 {
+                    attempt.error("Too many redirects.")
+                } else if matches!( attempt.url().host_str(), Some(host) if trusted_hosts.contains(host) )
+                {
+                        attempt.follow()
+                } else {
+                    let message = format!(
+                        "Aborting redirect request to unknown host '{}'.",
+                        attempt.url().host_str().unwrap_or("")
+                    );
+                    attempt.error(message)
+                }
+            }))
+            .default_headers(headers)
+            .build()
+            .map_err(|err| err.into())
+    }
+}
+
 impl Client {
     #[allow(clippy::new_ret_no_self)]
     pub fn new() -> ClientBuilder {
         ClientBuilder::new()
     }
 
+    /// Connects to the JMAP API Session URL, applying `options` to the session
+    /// fetch and all subsequent requests.
+    #[maybe_async::maybe_async]
+    pub async fn connect_with_options(
+        url: &str,
+        credentials: impl Into<Credentials>,
+        options: ClientOptions,
+    ) -> crate::Result<Client> {
+        let mut builder = ClientBuilder::new()
+            .credentials(credentials)
+            .headers(options.headers);
+        if let Some(timeout) = options.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(user_agent) = options.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        builder.connect(url).await
+    }
+
+    /// Connects to the server at `JMAP_URL`, authenticating with the bearer
+    /// token in `JMAP_TOKEN` or, if unset, with `JMAP_USER` and `JMAP_PASSWORD`.
+    #[maybe_async::maybe_async]
+    pub async fn connect_from_env() -> crate::Result<Client> {
+        let (url, credentials) = env_settings(|name| std::env::var(name).ok())?;
+        ClientBuilder::new()
+            .credentials(credentials)
+            .connect(&url)
+            .await
+    }
+
+    /// Connects to the JMAP API Session URL, making up to `attempts` attempts
+    /// while the server cannot be reached, see
+    /// [ClientBuilder.connect_with_retry()](struct.ClientBuilder.html#method.connect_with_retry).
+    #[maybe_async::maybe_async]
+    pub async fn connect_with_retry(
+        url: &str,
+        credentials: impl Into<Credentials>,
+        attempts: u32,
+        delay: Duration,
+    ) -> crate::Result<Client> {
+        ClientBuilder::new()
+            .credentials(credentials)
+            .connect_with_retry(url, attempts, delay)
+            .await
+    }
+
+    /// Fetches the JMAP API Session at `url` to check `credentials`, without
+    /// setting up a client, see
+    /// [ClientBuilder.check_credentials()](struct.ClientBuilder.html#method.check_credentials).
+    #[maybe_async::maybe_async]
+    pub async fn check_credentials(
+        url: &str,
+        credentials: impl Into<Credentials>,
+    ) -> crate::Result<()> {
+        ClientBuilder::new()
+            .credentials(credentials)
+            .check_credentials(url)
+            .await
+    }
+
+    /// Sets the timeout for all the requests to the JMAP API. A zero duration
+    /// disables the timeout.
     pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
         self.timeout = timeout;
         self
     }
 
+    /// Disables the request timeout, useful for large downloads over slow links.
+    ///
+    /// # Warning
+    /// Without a timeout a request to an unresponsive server will never complete.
+    pub fn set_no_timeout(&mut self) -> &mut Self {
+        self.timeout = Duration::ZERO;
+        self
+    }
+
+    /// Sends `Accept-Language` with all requests, asking the server to localize
+    /// human-readable fields. Invalid header values are ignored.
+    pub fn set_accept_language(&mut self, accept_language: &str) -> &mut Self {
+        if let Ok(value) = header::HeaderValue::from_str(accept_language) {
+            self.headers.insert(header::ACCEPT_LANGUAGE, value);
+        }
+        self
+    }
+
+    /// Sends a header named `header_name` with every API request, set to a
+    /// value returned by `generator` for each request, for example to trace
+    /// requests across gateways. Retries of a request keep its value. Invalid
+    /// header values are ignored.
+    pub fn set_trace_id_generator(
+        &mut self,
+        header_name: header::HeaderName,
+        generator: impl Fn() -> String + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.trace_id = Some(TraceId {
+            header_name,
+            generator: Box::new(generator),
+        });
+        self
+    }
+
+    fn trace_id(&self) -> Option<TraceIdHeader> {
+        let trace_id = self.trace_id.as_ref()?;
+        header::HeaderValue::from_str(&(trace_id.generator)())
+            .ok()
+            .map(|value| (trace_id.header_name.clone(), value))
+    }
+
+    fn with_trace_id(request: RequestBuilder, trace_id: Option<&TraceIdHeader>) -> RequestBuilder {
+        match trace_id {
+            Some((header_name, value)) => request.header(header_name.clone(), value.clone()),
+            None => request,
+        }
+    }
+
     pub fn set_follow_redirects(
         &mut self,
         trusted_hosts: impl IntoIterator<Item = impl Into<String>>,
@@ -296,8 +586,179 @@ impl Client {
         self.timeout
     }
 
+    /// Sets how long to wait for the server to answer when opening an event
+    /// source, 10 seconds by default. Once the stream is established this
+    /// timeout no longer applies. A zero duration disables the timeout.
+    #[cfg(feature = "event-source")]
+    pub fn set_event_source_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.event_source_timeout = timeout;
+        self
+    }
+
+    #[cfg(feature = "event-source")]
+    pub fn event_source_timeout(&self) -> Duration {
+        self.event_source_timeout
+    }
+
+    /// Compresses request bodies larger than 1KB using gzip.
+    ///
+    /// If the server rejects a compressed request with `415 Unsupported Media Type`,
+    /// the request is sent again uncompressed and compression is not attempted
+    /// again for this `Client`.
+    pub fn set_request_compression(&mut self, request_compression: bool) -> &mut Self {
+        self.request_compression = request_compression;
+        self
+    }
+
+    /// Enables or disables checking `Email/query` comparators against the
+    /// `emailQuerySortOptions` advertised by the server before sending a request.
+    ///
+    /// Validation is enabled by default, it can be disabled for servers that
+    /// support sorting on more properties than they advertise.
+    pub fn set_validate_sort_options(&mut self, validate_sort_options: bool) -> &mut Self {
+        self.validate_sort_options = validate_sort_options;
+        self
+    }
+
+    pub fn validate_sort_options(&self) -> bool {
+        self.validate_sort_options
+    }
+
+    /// Enables rejecting API responses with a missing or `null`
+    /// `methodResponses`, which are otherwise treated as an empty list.
+    pub fn set_strict_responses(&mut self, strict_responses: bool) -> &mut Self {
+        self.strict_responses = strict_responses;
+        self
+    }
+
+    pub fn strict_responses(&self) -> bool {
+        self.strict_responses
+    }
+
+    /// Enables adding the result references of a method call to the
+    /// description of its `invalidResultReference` errors, which helps
+    /// finding the broken link in a chain of calls. Enabled by default in
+    /// debug builds.
+    pub fn set_describe_references(&mut self, describe_references: bool) -> &mut Self {
+        self.describe_references = describe_references;
+        self
+    }
+
+    pub fn describe_references(&self) -> bool {
+        self.describe_references
+    }
+
+    /// Sets how many times API requests and uploads are retried after a
+    /// `429 Too Many Requests` response, waiting for the time given in its
+    /// `Retry-After` header (one second if missing, at most one minute).
+    /// Defaults to zero, which returns the error right away.
+    pub fn set_rate_limit_retries(&mut self, rate_limit_retries: u32) -> &mut Self {
+        self.rate_limit_retries = rate_limit_retries;
+        self
+    }
+
+    pub fn rate_limit_retries(&self) -> u32 {
+        self.rate_limit_retries
+    }
+
+    /// Sends `request`, retrying it as configured by
+    /// [`Client::set_rate_limit_retries`] while the server is rate limiting.
+    #[maybe_async::maybe_async]
+    pub(crate) async fn send_retrying(
+        &self,
+        mut request: RequestBuilder,
+    ) -> crate::Result<Response> {
+        let mut retries = 0;
+        loop {
+            let next_request = if retries < self.rate_limit_retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            let response = request.send().await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS
+                || retries >= self.rate_limit_retries
+            {
+                return Ok(response);
+            }
+            request = next_request.ok_or_else(|| {
+                Error::from("Rate limited by the server, the request body cannot be sent again.")
+            })?;
+            retries += 1;
+            sleep(retry_after(&response)).await;
+        }
+    }
+
+    /// Stops sending API requests for `cooldown` after `threshold` consecutive
+    /// failures, which are transport errors and 5xx responses. Requests made
+    /// while the circuit is open fail with [`Error::CircuitOpen`]. Once the
+    /// cooldown expires, a single failure opens the circuit again while a
+    /// success closes it.
+    pub fn enable_circuit_breaker(&mut self, threshold: u32, cooldown: Duration) -> &mut Self {
+        self.circuit_breaker = Some(CircuitBreaker {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Default::default(),
+        });
+        self
+    }
+
+    pub fn disable_circuit_breaker(&mut self) -> &mut Self {
+        self.circuit_breaker = None;
+        self
+    }
+
+    /// Overrides the number of uploads allowed to run at the same time, which
+    /// defaults to the session's `maxConcurrentUpload`.
+    pub fn set_max_concurrent_uploads(&mut self, max_concurrent_uploads: usize) -> &mut Self {
+        self.upload_limiter = TransferLimiter::new(max_concurrent_uploads);
+        self
+    }
+
+    pub fn max_concurrent_uploads(&self) -> usize {
+        self.upload_limiter.max_permits
+    }
+
+    /// Overrides the number of downloads allowed to run at the same time,
+    /// which defaults to the session's `maxConcurrentRequests`. Downloads do
+    /// not count towards the upload limit.
+    pub fn set_max_concurrent_downloads(&mut self, max_concurrent_downloads: usize) -> &mut Self {
+        self.download_limiter = TransferLimiter::new(max_concurrent_downloads);
+        self
+    }
+
+    pub fn max_concurrent_downloads(&self) -> usize {
+        self.download_limiter.max_permits
+    }
+
+    /// Sets the size in bytes above which [`Client::email_create`] uploads a
+    /// body value as a blob instead of sending it inline. Defaults to 1 MiB.
+    pub fn set_body_upload_threshold(&mut self, body_upload_threshold: usize) -> &mut Self {
+        self.body_upload_threshold = body_upload_threshold;
+        self
+    }
+
+    pub fn body_upload_threshold(&self) -> usize {
+        self.body_upload_threshold
+    }
+
+    #[maybe_async::maybe_async]
+    pub(crate) async fn acquire_upload(&self) -> TransferPermit<'_> {
+        self.upload_limiter.acquire().await
+    }
+
+    #[maybe_async::maybe_async]
+    pub(crate) async fn acquire_download(&self) -> TransferPermit<'_> {
+        self.download_limiter.acquire().await
+    }
+
+    pub(crate) fn http_builder(&self) -> HttpClientBuilder {
+        self.http2
+            .apply(with_timeout(HttpClient::builder(), self.timeout))
+    }
+
     pub fn session(&self) -> Arc<Session> {
-        self.session.lock().clone()
+        self.session.lock().session.clone()
     }
 
     pub fn session_url(&self) -> &str {
@@ -326,72 +787,251 @@ impl Client {
         })
     }
 
+    /// Sends a request to the API endpoint.
+    ///
+    /// When using the async client, dropping the returned future cancels the
+    /// request by closing its connection. The client holds no per-request
+    /// state, so it remains usable after a cancellation.
     #[maybe_async::maybe_async]
     pub async fn send<R>(
         &self,
-        request: &request::Request<'_>,
+        request: &(impl Serialize + ?Sized),
     ) -> crate::Result<response::Response<R>>
     where
         R: DeserializeOwned,
     {
-        let response: response::Response<R> = serde_json::from_slice(
-            &Client::handle_error(
-                HttpClient::builder()
-                    .redirect(self.redirect_policy())
-                    .danger_accept_invalid_certs(self.accept_invalid_certs)
-                    .timeout(self.timeout)
-                    .default_headers(self.headers.clone())
-                    .build()?
-                    .post(&self.api_url)
-                    .body(serde_json::to_string(&request)?)
-                    .send()
-                    .await?,
-            )
-            .await?
-            .bytes()
-            .await?,
-        )?;
+        self.send_with_raw(request)
+            .await
+            .map(|(response, _)| response)
+    }
+
+    /// Sends a request and returns the parsed response together with the raw
+    /// JSON bytes received from the server.
+    #[maybe_async::maybe_async]
+    pub async fn send_with_raw<R>(
+        &self,
+        request: &(impl Serialize + ?Sized),
+    ) -> crate::Result<(response::Response<R>, Bytes)>
+    where
+        R: DeserializeOwned,
+    {
+        let body = serde_json::to_vec(&request)?;
+        let http_client = self
+            .http_builder()
+            .redirect(self.redirect_policy())
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .default_headers(self.headers.clone())
+            .build()?;
+
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.check()?;
+        }
+        let http_response = self
+            .post_api_or_relocate(&http_client, &body, self.trace_id().as_ref())
+            .await;
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record(match &http_response {
+                Ok(response) => response.status().is_server_error(),
+                Err(_) => true,
+            });
+        }
+
+        let raw = Client::handle_error(http_response?).await?.bytes().await?;
+        let response: response::Response<R> = serde_json::from_slice(&raw)?;
+        if self.strict_responses && response.method_responses().is_empty() {
+            serde_json::from_slice::<response::StrictResponse>(&raw)?;
+        }
 
-        if response.session_state() != self.session.lock().state() {
+        if response.session_state() != self.session.lock().session.state() {
             self.session_updated.store(false, Ordering::Relaxed);
         }
 
-        Ok(response)
+        Ok((response, raw))
     }
 
+    /// Posts `body` to the API URL as is and returns the raw response body,
+    /// for vendor extensions that do not use JMAP requests. The body is sent
+    /// with the given content type, or `application/json` when `None`.
     #[maybe_async::maybe_async]
-    pub async fn refresh_session(&self) -> crate::Result<()> {
-        let session: Session = serde_json::from_slice(
-            &Client::handle_error(
-                HttpClient::builder()
-                    .timeout(Duration::from_millis(DEFAULT_TIMEOUT_MS))
-                    .danger_accept_invalid_certs(self.accept_invalid_certs)
-                    .redirect(self.redirect_policy())
-                    .default_headers(self.headers.clone())
-                    .build()?
-                    .get(&self.session_url)
-                    .send()
-                    .await?,
-            )
+    pub async fn send_raw(
+        &self,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> crate::Result<Bytes> {
+        let http_client = self
+            .http_builder()
+            .redirect(self.redirect_policy())
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .default_headers(self.headers.clone())
+            .build()?;
+        let mut request = Client::with_trace_id(
+            http_client.post(self.session().api_url()),
+            self.trace_id().as_ref(),
+        )
+        .body(body);
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+
+        Client::handle_error(self.send_retrying(request).await?)
             .await?
             .bytes()
-            .await?,
-        )?;
-        *self.session.lock() = Arc::new(session);
+            .await
+            .map_err(|err| err.into())
+    }
+
+    #[maybe_async::maybe_async]
+    async fn post_api_or_relocate(
+        &self,
+        http_client: &HttpClient,
+        body: &[u8],
+        trace_id: Option<&TraceIdHeader>,
+    ) -> crate::Result<Response> {
+        let api_url = self.session().api_url().to_string();
+        let http_response = self.post_api(http_client, &api_url, body, trace_id).await?;
+        if http_response.status() == StatusCode::NOT_FOUND {
+            // The API URL may have moved, retry once if the session points elsewhere.
+            self.refresh_session().await?;
+            let new_api_url = self.session().api_url().to_string();
+            if new_api_url != api_url {
+                return self
+                    .post_api(http_client, &new_api_url, body, trace_id)
+                    .await;
+            }
+        }
+        Ok(http_response)
+    }
+
+    #[maybe_async::maybe_async]
+    async fn post_api(
+        &self,
+        http_client: &HttpClient,
+        api_url: &str,
+        body: &[u8],
+        trace_id: Option<&TraceIdHeader>,
+    ) -> crate::Result<Response> {
+        if self.request_compression
+            && body.len() >= COMPRESSION_THRESHOLD
+            && !self.compression_unsupported.load(Ordering::Relaxed)
+        {
+            let mut encoder =
+                GzEncoder::new(Vec::with_capacity(body.len()), Compression::default());
+            encoder.write_all(body)?;
+            let response = self
+                .send_retrying(
+                    Client::with_trace_id(http_client.post(api_url), trace_id)
+                        .header(header::CONTENT_ENCODING, "gzip")
+                        .body(encoder.finish()?),
+                )
+                .await?;
+
+            if response.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+                // The server does not accept compressed requests, stop compressing.
+                self.compression_unsupported.store(true, Ordering::Relaxed);
+            } else {
+                return Ok(response);
+            }
+        }
+        self.send_retrying(
+            Client::with_trace_id(http_client.post(api_url), trace_id).body(body.to_vec()),
+        )
+        .await
+    }
+
+    /// Fetches the session again. If the server returned an ETag with the
+    /// current session it is sent as `If-None-Match`, and a `304 Not Modified`
+    /// response keeps the current session.
+    #[maybe_async::maybe_async]
+    pub async fn refresh_session(&self) -> crate::Result<()> {
+        let mut request = self
+            .http2
+            .apply(HttpClient::builder())
+            .timeout(Duration::from_millis(DEFAULT_TIMEOUT_MS))
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .redirect(self.redirect_policy())
+            .default_headers(self.headers.clone())
+            .build()?
+            .get(&self.session_url);
+        let session_etag = self.session_etag.lock().clone();
+        if let Some(session_etag) = session_etag {
+            request = request.header(header::IF_NONE_MATCH, session_etag);
+        }
+
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            self.session_updated.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+        let response = Client::handle_error(response).await?;
+        let session_etag = etag(&response);
+        let final_url = response.url().clone();
+        let mut session: Session = serde_json::from_slice(&response.bytes().await?)?;
+        session.resolve_urls(&final_url);
+        *self.session.lock() = ClientSession::new(session)?;
+        *self.session_etag.lock() = session_etag;
         self.session_updated.store(true, Ordering::Relaxed);
         Ok(())
     }
 
+    /// Returns whether the server supports a method that is not modeled by this
+    /// crate. The session must advertise `capability_urn`, in which case the
+    /// method is probed with an empty set of arguments: an `unknownMethod` error
+    /// means it is not supported, while any other result (including
+    /// `invalidArguments`) means the server recognised it.
+    #[maybe_async::maybe_async]
+    pub async fn supports_method(
+        &self,
+        capability_urn: &str,
+        method_name: &str,
+    ) -> crate::Result<bool> {
+        if !self.session().has_capability(capability_urn) {
+            return Ok(false);
+        }
+
+        let probe = serde_json::json!({
+            "using": [URI::Core.as_ref(), capability_urn],
+            "methodCalls": [[method_name, {}, "s0"]],
+        });
+        self.send::<(String, serde_json::Value, String)>(&probe)
+            .await
+            .map(|response| probe_result(&response))
+    }
+
     pub fn is_session_updated(&self) -> bool {
         self.session_updated.load(Ordering::Relaxed)
     }
 
-    pub fn set_default_account_id(&mut self, defaul_account_id: impl Into<String>) -> &mut Self {
+    /// State of the session held by the client, updated whenever the session
+    /// is refreshed. Applications can persist it and compare it with a freshly
+    /// fetched session to detect changes across restarts.
+    pub fn known_session_state(&self) -> String {
+        self.session.lock().session.state().to_string()
+    }
+
+    /// Sync cursors known to the client, updated by
+    /// [`Client::email_sync_changes`] and by applications through
+    /// [`Client::update_sync_state`]. Applications can
+    /// persist them and restore them on the next launch with
+    /// [`Client::import_sync_state`].
+    pub fn export_sync_state(&self) -> SyncState {
+        self.sync_state.lock().clone()
+    }
+
+    /// Replaces the sync cursors known to the client.
+    pub fn import_sync_state(&self, sync_state: SyncState) {
+        *self.sync_state.lock() = sync_state;
+    }
+
+    pub fn update_sync_state(&self, f: impl FnOnce(&mut SyncState)) {
+        f(&mut self.sync_state.lock());
+    }
+
+    pub fn set_default_account_id(&mut self, defaul_account_id: impl Into<AccountId>) -> &mut Self {
         self.default_account_id = defaul_account_id.into();
         self
     }
 
-    pub fn default_account_id(&self) -> &str {
+    pub fn default_account_id(&self) -> &AccountId {
         &self.default_account_id
     }
 
@@ -399,17 +1039,16 @@ impl Client {
         Request::new(self)
     }
 
-    pub fn download_url(&self) -> &[URLPart<blob::URLParameter>] {
-        &self.download_url
+    pub fn download_url(&self) -> Arc<[URLPart<blob::URLParameter>]> {
+        self.session.lock().download_url.clone()
     }
 
-    pub fn upload_url(&self) -> &[URLPart<blob::URLParameter>] {
-        &self.upload_url
+    pub fn upload_url(&self) -> Arc<[URLPart<blob::URLParameter>]> {
+        self.session.lock().upload_url.clone()
     }
 
-    #[cfg(feature = "async")]
-    pub fn event_source_url(&self) -> &[URLPart<crate::event_source::URLParameter>] {
-        &self.event_source_url
+    pub fn event_source_url(&self) -> Arc<[URLPart<crate::event_source::URLParameter>]> {
+        self.session.lock().event_source_url.clone()
     }
 
     #[maybe_async::maybe_async]
@@ -425,19 +1064,32 @@ impl Client {
                 &response.bytes().await?,
             )?))
         } else {
-            Err(Error::Server(format!("{}", response.status())))
+            Err(Error::Server {
+                status: response.status().as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            })
         }
     }
 }
 
 impl Credentials {
     pub fn basic(username: &str, password: &str) -> Self {
-        Credentials::Basic(base64::encode(format!("{}:{}", username, password)))
+        Credentials::Basic(crate::core::base64::encode(format!(
+            "{}:{}",
+            username, password
+        )))
     }
 
     pub fn bearer(token: impl Into<String>) -> Self {
         Credentials::Bearer(token.into())
     }
+
+    fn authorization(&self) -> String {
+        match self {
+            Credentials::Basic(s) => format!("Basic {}", s),
+            Credentials::Bearer(s) => format!("Bearer {}", s),
+        }
+    }
 }
 
 impl From<&str> for Credentials {
@@ -464,12 +1116,271 @@ impl From<(String, String)> for Credentials {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::core::response::{Response, TaggedMethodResponse};
+#[derive(Debug, Clone, Copy, Default)]
+struct Http2Settings {
+    prior_knowledge: bool,
+    adaptive_window: bool,
+    initial_stream_window_size: Option<u32>,
+    initial_connection_window_size: Option<u32>,
+}
 
-    #[test]
-    fn test_deserialize() {
+impl Http2Settings {
+    fn apply(&self, mut builder: HttpClientBuilder) -> HttpClientBuilder {
+        if self.prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if self.adaptive_window {
+            builder = builder.http2_adaptive_window(true);
+        }
+        if let Some(size) = self.initial_stream_window_size {
+            builder = builder.http2_initial_stream_window_size(size);
+        }
+        if let Some(size) = self.initial_connection_window_size {
+            builder = builder.http2_initial_connection_window_size(size);
+        }
+        builder
+    }
+}
+
+// The session together with its parsed URL templates, which are replaced
+// at once when the session is refreshed.
+struct ClientSession {
+    session: Arc<Session>,
+    upload_url: Arc<[URLPart<blob::URLParameter>]>,
+    download_url: Arc<[URLPart<blob::URLParameter>]>,
+    event_source_url: Arc<[URLPart<crate::event_source::URLParameter>]>,
+}
+
+impl ClientSession {
+    fn new(session: Session) -> crate::Result<Self> {
+        Ok(ClientSession {
+            upload_url: URLPart::parse(session.upload_url())?.into(),
+            download_url: URLPart::parse(session.download_url())?.into(),
+            event_source_url: URLPart::parse(session.event_source_url())?.into(),
+            session: Arc::new(session),
+        })
+    }
+}
+
+struct TraceId {
+    header_name: header::HeaderName,
+    generator: Box<dyn Fn() -> String + Send + Sync>,
+}
+
+type TraceIdHeader = (header::HeaderName, header::HeaderValue);
+
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: parking_lot::Mutex<CircuitState>,
+}
+
+#[derive(Default)]
+struct CircuitState {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn check(&self) -> crate::Result<()> {
+        match self.state.lock().open_until {
+            Some(open_until) if Instant::now() < open_until => Err(Error::CircuitOpen),
+            _ => Ok(()),
+        }
+    }
+
+    fn record(&self, failed: bool) {
+        let mut state = self.state.lock();
+        if failed {
+            state.failures += 1;
+            if state.failures >= self.threshold {
+                state.open_until = Some(Instant::now() + self.cooldown);
+            }
+        } else {
+            *state = CircuitState::default();
+        }
+    }
+}
+
+/// Limits the number of uploads or downloads in flight.
+struct TransferLimiter {
+    max_permits: usize,
+    #[cfg(feature = "async")]
+    semaphore: tokio::sync::Semaphore,
+    #[cfg(feature = "blocking")]
+    permits: parking_lot::Mutex<usize>,
+    #[cfg(feature = "blocking")]
+    released: parking_lot::Condvar,
+}
+
+#[cfg(feature = "async")]
+pub(crate) type TransferPermit<'x> = tokio::sync::SemaphorePermit<'x>;
+
+#[cfg(feature = "blocking")]
+pub(crate) struct TransferPermit<'x>(&'x TransferLimiter);
+
+impl TransferLimiter {
+    fn new(max_permits: usize) -> Self {
+        let max_permits = max_permits.max(1);
+        TransferLimiter {
+            max_permits,
+            #[cfg(feature = "async")]
+            semaphore: tokio::sync::Semaphore::new(max_permits),
+            #[cfg(feature = "blocking")]
+            permits: max_permits.into(),
+            #[cfg(feature = "blocking")]
+            released: parking_lot::Condvar::new(),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    async fn acquire(&self) -> TransferPermit<'_> {
+        // The semaphore is never closed.
+        self.semaphore.acquire().await.unwrap()
+    }
+
+    #[cfg(feature = "blocking")]
+    fn acquire(&self) -> TransferPermit<'_> {
+        let mut permits = self.permits.lock();
+        while *permits == 0 {
+            self.released.wait(&mut permits);
+        }
+        *permits -= 1;
+        TransferPermit(self)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Drop for TransferPermit<'_> {
+    fn drop(&mut self) {
+        *self.0.permits.lock() += 1;
+        self.0.released.notify_one();
+    }
+}
+
+fn env_settings(var: impl Fn(&str) -> Option<String>) -> crate::Result<(String, Credentials)> {
+    let url = var("JMAP_URL");
+    let credentials = match (var("JMAP_TOKEN"), var("JMAP_USER"), var("JMAP_PASSWORD")) {
+        (Some(token), _, _) => Ok(Credentials::bearer(token)),
+        (None, Some(user), Some(password)) => Ok(Credentials::basic(&user, &password)),
+        (None, user, password) => Err(format!(
+            "JMAP_TOKEN or {}",
+            [("JMAP_USER", user), ("JMAP_PASSWORD", password)]
+                .into_iter()
+                .filter(|(_, value)| value.is_none())
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+                .join(" and ")
+        )),
+    };
+
+    match (url, credentials) {
+        (Some(url), Ok(credentials)) => Ok((url, credentials)),
+        (url, credentials) => Err(Error::Internal(format!(
+            "Missing environment variables: {}",
+            url.is_none()
+                .then_some("JMAP_URL".to_string())
+                .into_iter()
+                .chain(credentials.err())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+fn retry_after(response: &Response) -> Duration {
+    response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .map(Duration::from_secs)
+                .or_else(|| {
+                    chrono::DateTime::parse_from_rfc2822(value.trim())
+                        .ok()
+                        .map(|date| {
+                            (date.timestamp() - chrono::Utc::now().timestamp()).max(0) as u64
+                        })
+                        .map(Duration::from_secs)
+                })
+        })
+        .unwrap_or(DEFAULT_RETRY_AFTER)
+        .min(MAX_RETRY_AFTER)
+}
+
+#[cfg(feature = "async")]
+async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(feature = "blocking")]
+fn sleep(duration: Duration) {
+    std::thread::sleep(duration);
+}
+
+fn etag(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(String::from)
+}
+
+fn with_timeout(builder: HttpClientBuilder, timeout: Duration) -> HttpClientBuilder {
+    if !timeout.is_zero() {
+        builder.timeout(timeout)
+    } else {
+        #[cfg(feature = "blocking")]
+        {
+            builder.timeout(None)
+        }
+        #[cfg(feature = "async")]
+        {
+            builder
+        }
+    }
+}
+
+fn probe_result(response: &response::Response<(String, serde_json::Value, String)>) -> bool {
+    !response
+        .method_responses()
+        .iter()
+        .any(|(name, arguments, _)| {
+            name == "error"
+                && arguments.get("type").and_then(|t| t.as_str()) == Some("unknownMethod")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "async")]
+    use std::sync::Arc;
+
+    use crate::core::response::{Response, TaggedMethodResponse};
+
+    #[test]
+    fn test_probe_result() {
+        let unknown: Response<(String, serde_json::Value, String)> = serde_json::from_slice(
+            br#"{"sessionState": "123", "methodResponses": [
+                ["error", {"type": "unknownMethod"}, "s0"]]}"#,
+        )
+        .unwrap();
+        assert!(!super::probe_result(&unknown));
+
+        let invalid: Response<(String, serde_json::Value, String)> = serde_json::from_slice(
+            br#"{"sessionState": "123", "methodResponses": [
+                ["error", {"type": "invalidArguments"}, "s0"]]}"#,
+        )
+        .unwrap();
+        assert!(super::probe_result(&invalid));
+    }
+
+    #[test]
+    fn test_deserialize() {
         let _r: Response<TaggedMethodResponse> = serde_json::from_slice(
             br#"{"sessionState": "123", "methodResponses": [[ "Email/query", {
                 "accountId": "A1",
@@ -509,4 +1420,790 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_no_timeout() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+        use std::time::Duration;
+
+        let server = TestServer::start(|_| {
+            TestResponse::new(200, "").chunked(
+                vec![b"slow".to_vec(), b" but ".to_vec(), b"steady".to_vec()],
+                Duration::from_millis(100),
+            )
+        });
+
+        block_on(async {
+            let mut client = server.connect().await;
+            client.set_timeout(Duration::from_millis(50));
+            assert!(client.download("blob").await.is_err());
+
+            client.set_timeout(Duration::ZERO);
+            assert_eq!(client.download("blob").await.unwrap(), b"slow but steady");
+
+            client.set_timeout(Duration::from_secs(1)).set_no_timeout();
+            assert_eq!(client.download("blob").await.unwrap(), b"slow but steady");
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_request_compression() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+        use std::io::Read;
+
+        const RESPONSE: &str = r#"{"sessionState": "s1", "methodResponses": []}"#;
+        let request = serde_json::json!({
+            "using": ["urn:ietf:params:jmap:core"],
+            "methodCalls": [["Core/echo", {"data": "x".repeat(4096)}, "s0"]],
+        });
+
+        let server = TestServer::start(|_| TestResponse::json(200, RESPONSE));
+        block_on(async {
+            let mut client = server.connect().await;
+            client.set_request_compression(true);
+            client.send::<serde_json::Value>(&request).await.unwrap();
+        });
+
+        let requests = server.requests();
+        assert_eq!(requests[0].header("Content-Encoding"), Some("gzip"));
+        let mut body = String::new();
+        flate2::read::GzDecoder::new(&requests[0].body[..])
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            request
+        );
+
+        // Servers rejecting compressed bodies receive the request uncompressed.
+        let server = TestServer::start(|request| {
+            if request.header("Content-Encoding").is_some() {
+                TestResponse::new(415, "")
+            } else {
+                TestResponse::json(200, RESPONSE)
+            }
+        });
+        block_on(async {
+            let mut client = server.connect().await;
+            client.set_request_compression(true);
+            client.send::<serde_json::Value>(&request).await.unwrap();
+            client.send::<serde_json::Value>(&request).await.unwrap();
+        });
+
+        let encodings = server
+            .requests()
+            .iter()
+            .map(|request| request.header("Content-Encoding").is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(encodings, [true, false, false]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_send_with_raw() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        const RESPONSE: &str = r#"{"sessionState": "s1", "methodResponses": [
+            ["Core/echo", {"data": "x"}, "s0"]]}"#;
+        let server = TestServer::start(|_| TestResponse::json(200, RESPONSE));
+
+        let (response, raw) = block_on(async {
+            let client = server.connect().await;
+            client
+                .send_with_raw::<serde_json::Value>(&serde_json::json!({
+                    "using": ["urn:ietf:params:jmap:core"],
+                    "methodCalls": [["Core/echo", {"data": "x"}, "s0"]],
+                }))
+                .await
+                .unwrap()
+        });
+
+        assert_eq!(raw, RESPONSE.as_bytes());
+        let raw: serde_json::Value = serde_json::from_slice(&raw).unwrap();
+        assert_eq!(raw["sessionState"], response.session_state());
+        assert_eq!(
+            raw["methodResponses"],
+            serde_json::to_value(response.method_responses()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_missing_method_responses() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|request| {
+            if request.body.len() % 2 == 0 {
+                TestResponse::json(200, r#"{"sessionState": "s1"}"#)
+            } else {
+                TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": null}"#)
+            }
+        });
+
+        block_on(async {
+            let mut client = server.connect().await;
+            for request in [
+                serde_json::json!({"using": [], "methodCalls": []}),
+                serde_json::json!({"using": [], "methodCalls": [], "x": 10}),
+            ] {
+                let response = client.send::<serde_json::Value>(&request).await.unwrap();
+                assert!(response.method_responses().is_empty());
+            }
+
+            client.set_strict_responses(true);
+            assert!(matches!(
+                client
+                    .send::<serde_json::Value>(&serde_json::json!({"using": [], "methodCalls": []}))
+                    .await,
+                Err(crate::Error::Json(_))
+            ));
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_refresh_session_not_modified() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(200, r#"{"sessionState": "s2", "methodResponses": []}"#)
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let session = client.session();
+            client
+                .send::<serde_json::Value>(&serde_json::json!({
+                    "using": ["urn:ietf:params:jmap:core"],
+                    "methodCalls": [],
+                }))
+                .await
+                .unwrap();
+            assert!(!client.is_session_updated());
+
+            client.refresh_session().await.unwrap();
+            assert!(client.is_session_updated());
+            assert!(Arc::ptr_eq(&session, &client.session()));
+        });
+
+        let session_requests = server.session_requests();
+        assert_eq!(session_requests.len(), 2);
+        assert_eq!(session_requests[0].header("If-None-Match"), None);
+        assert_eq!(session_requests[1].header("If-None-Match"), Some("\"s1\""));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_accept_language() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#)
+        });
+        block_on(async {
+            let mut client = server.connect().await;
+            client.set_accept_language("de-DE, de;q=0.9");
+            client
+                .send::<serde_json::Value>(&serde_json::json!({
+                    "using": ["urn:ietf:params:jmap:core"],
+                    "methodCalls": [],
+                }))
+                .await
+                .unwrap();
+        });
+
+        assert_eq!(
+            server.requests()[0].header("Accept-Language"),
+            Some("de-DE, de;q=0.9")
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_api_url_moved() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|request| {
+            if request.path == "/api2" {
+                TestResponse::json(200, r#"{"sessionState": "s2", "methodResponses": []}"#)
+            } else if request.path.starts_with("/upload2/") {
+                TestResponse::json(
+                    201,
+                    r#"{"accountId": "a", "blobId": "b1", "type": "text/plain", "size": 5}"#,
+                )
+            } else {
+                TestResponse::new(404, "")
+            }
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let url = server.url.clone();
+            server.update_session(|session| {
+                session["apiUrl"] = format!("{}/api2", url).into();
+                session["uploadUrl"] = format!("{}/upload2/{{accountId}}/", url).into();
+                session["state"] = "s2".into();
+            });
+            client
+                .send::<serde_json::Value>(&serde_json::json!({
+                    "using": ["urn:ietf:params:jmap:core"],
+                    "methodCalls": [],
+                }))
+                .await
+                .unwrap();
+            client
+                .upload(None, b"hello".to_vec(), Some("text/plain"))
+                .await
+                .unwrap();
+        });
+
+        let paths = server
+            .requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["/api", "/api2", "/upload2/a/"]);
+        assert_eq!(server.session_requests().len(), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_connect_with_options() {
+        use super::{Client, ClientOptions, Credentials};
+        use crate::test_server::{block_on, TestResponse, TestServer};
+        use reqwest::header::{HeaderMap, HeaderValue};
+        use std::time::Duration;
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#)
+        });
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Tenant", HeaderValue::from_static("acme"));
+        let client = block_on(Client::connect_with_options(
+            &server.url,
+            Credentials::bearer("token"),
+            ClientOptions {
+                timeout: Duration::from_secs(3).into(),
+                headers,
+                user_agent: "my-app/1.0".to_string().into(),
+            },
+        ))
+        .unwrap();
+        assert_eq!(client.timeout(), Duration::from_secs(3));
+
+        let session_request = &server.session_requests()[0];
+        assert_eq!(session_request.header("X-Tenant"), Some("acme"));
+        assert_eq!(session_request.header("User-Agent"), Some("my-app/1.0"));
+        assert_eq!(
+            session_request.header("Authorization"),
+            Some("Bearer token")
+        );
+    }
+
+    #[cfg(all(feature = "async", not(feature = "event-source")))]
+    #[test]
+    fn test_without_event_source() {
+        use crate::{
+            core::session::URLPart,
+            event_source::URLParameter,
+            test_server::{block_on, TestResponse, TestServer},
+        };
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#)
+        });
+        block_on(async {
+            let client = server.connect().await;
+            client.build().send().await.unwrap();
+            assert!(client
+                .event_source_url()
+                .iter()
+                .any(|part| matches!(part, URLPart::Parameter(URLParameter::Types))));
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_trace_id_generator() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#)
+        });
+        block_on(async {
+            let mut client = server.connect().await;
+            let counter = AtomicUsize::new(0);
+            client.set_trace_id_generator(
+                reqwest::header::HeaderName::from_static("x-trace-id"),
+                move || format!("trace-{}", counter.fetch_add(1, Ordering::Relaxed)),
+            );
+            client.build().send().await.unwrap();
+            client.build().send().await.unwrap();
+        });
+
+        let trace_ids = server
+            .requests()
+            .iter()
+            .map(|request| request.header("X-Trace-Id").unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(trace_ids, ["trace-0", "trace-1"]);
+        assert!(server.session_requests()[0].header("X-Trace-Id").is_none());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_trace_id_kept_on_fallback() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|request| {
+            if request.header("Content-Encoding").is_some() {
+                TestResponse::new(415, "")
+            } else {
+                TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#)
+            }
+        });
+        block_on(async {
+            let mut client = server.connect().await;
+            let counter = AtomicUsize::new(0);
+            client.set_request_compression(true).set_trace_id_generator(
+                reqwest::header::HeaderName::from_static("x-trace-id"),
+                move || format!("trace-{}", counter.fetch_add(1, Ordering::Relaxed)),
+            );
+            let mut request = client.build();
+            request.echo(serde_json::json!({"data": "x".repeat(2048)}));
+            request.send().await.unwrap();
+        });
+
+        let trace_ids = server
+            .requests()
+            .iter()
+            .map(|request| request.header("X-Trace-Id").unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(trace_ids, ["trace-0", "trace-0"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_send_raw() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|request| TestResponse::new(200, request.body.clone()));
+        block_on(async {
+            let client = server.connect().await;
+            assert_eq!(
+                client
+                    .send_raw(b"<frob/>".to_vec(), Some("application/xml"))
+                    .await
+                    .unwrap()
+                    .as_ref(),
+                b"<frob/>"
+            );
+            client.send_raw(b"{}".to_vec(), None).await.unwrap();
+        });
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/api");
+        assert_eq!(requests[0].header("Content-Type"), Some("application/xml"));
+        assert_eq!(requests[1].header("Content-Type"), Some("application/json"));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_http2_settings() {
+        use super::{Client, Credentials};
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#)
+        });
+        block_on(async {
+            // Window settings only apply to HTTP/2 and leave HTTP/1 working.
+            let client = Client::new()
+                .credentials(Credentials::bearer("token"))
+                .http2_adaptive_window(true)
+                .http2_initial_stream_window_size(1024 * 1024)
+                .http2_initial_connection_window_size(4 * 1024 * 1024)
+                .connect(&server.url)
+                .await
+                .unwrap();
+            client.build().send().await.unwrap();
+
+            // With prior knowledge the HTTP/1 test server cannot be reached.
+            assert!(Client::new()
+                .credentials(Credentials::bearer("token"))
+                .http2_prior_knowledge()
+                .connect(&server.url)
+                .await
+                .is_err());
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_known_session_state() {
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(200, r#"{"sessionState": "s2", "methodResponses": []}"#)
+        });
+        block_on(async {
+            let client = server.connect().await;
+            assert_eq!(client.known_session_state(), "s1");
+
+            client.build().send().await.unwrap();
+            assert!(!client.is_session_updated());
+            assert_eq!(client.known_session_state(), "s1");
+
+            server.update_session(|session| session["state"] = "s2".into());
+            client.refresh_session().await.unwrap();
+            assert_eq!(client.known_session_state(), "s2");
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_sync_state_round_trip() {
+        use crate::{
+            core::changes::SyncState,
+            test_server::{block_on, TestResponse, TestServer},
+            TypeState,
+        };
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": [
+                    ["Email/changes", {
+                        "accountId": "a",
+                        "oldState": "e1",
+                        "newState": "e2",
+                        "hasMoreChanges": false,
+                        "created": ["m1"],
+                        "updated": [],
+                        "destroyed": []
+                    }, "s0"]
+                ]})
+                .to_string(),
+            )
+        });
+        let exported = block_on(async {
+            let client = server.connect().await;
+            assert!(client.export_sync_state().is_empty());
+            client.email_sync_changes("e1", None, 1).await.unwrap();
+            client.update_sync_state(|state| {
+                state.set_query_state("inbox", "q1");
+            });
+            serde_json::to_string(&client.export_sync_state()).unwrap()
+        });
+
+        let sync_state: SyncState = serde_json::from_str(&exported).unwrap();
+        assert_eq!(sync_state.state(&TypeState::Email), Some("e2"));
+        assert_eq!(sync_state.query_state("inbox"), Some("q1"));
+        block_on(async {
+            let client = server.connect().await;
+            client.import_sync_state(sync_state.clone());
+            assert_eq!(client.export_sync_state(), sync_state);
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_check_credentials() {
+        use super::{Client, Credentials};
+        use crate::{
+            test_server::{block_on, TestResponse, TestServer},
+            Error,
+        };
+
+        let server = TestServer::start(|_| TestResponse::new(500, ""));
+        let rejecting = {
+            use std::io::{Read, Write};
+
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            std::thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 4096];
+                let len = stream.read(&mut request).unwrap();
+                assert!(String::from_utf8_lossy(&request[..len]).contains("Bearer wrong"));
+                stream
+                    .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n")
+                    .unwrap();
+            });
+            url
+        };
+        let unreachable = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+
+        block_on(async {
+            Client::check_credentials(&server.url, Credentials::bearer("token"))
+                .await
+                .unwrap();
+            assert!(matches!(
+                Client::check_credentials(&rejecting, Credentials::bearer("wrong")).await,
+                Err(Error::Unauthorized)
+            ));
+            assert!(matches!(
+                Client::check_credentials(&unreachable, Credentials::bearer("token")).await,
+                Err(Error::Transport(err)) if err.is_connect()
+            ));
+        });
+        assert_eq!(server.session_requests().len(), 1);
+        assert!(server.requests().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_check_credentials_redirect() {
+        use super::{ClientBuilder, Credentials};
+        use crate::{
+            test_server::{block_on, TestResponse, TestServer},
+            Error,
+        };
+
+        let server = TestServer::start(|request| {
+            if request.path.starts_with("/portal/") {
+                TestResponse::new(200, "<html>Sign in to the network</html>")
+                    .header("Content-Type", "text/html")
+            } else {
+                TestResponse::new(307, "").header("Location", "/.well-known/jmap")
+            }
+        });
+
+        block_on(async {
+            ClientBuilder::new()
+                .credentials(Credentials::bearer("token"))
+                .follow_redirects(["127.0.0.1"])
+                .check_credentials(&format!("{}/old", server.url))
+                .await
+                .unwrap();
+            assert!(ClientBuilder::new()
+                .credentials(Credentials::bearer("token"))
+                .check_credentials(&format!("{}/old", server.url))
+                .await
+                .is_err());
+            assert!(matches!(
+                ClientBuilder::new()
+                    .credentials(Credentials::bearer("token"))
+                    .check_credentials(&format!("{}/portal", server.url))
+                    .await,
+                Err(Error::Json(_))
+            ));
+        });
+        let session_requests = server.session_requests();
+        assert_eq!(session_requests.len(), 1);
+        assert_eq!(
+            session_requests[0].header("Authorization"),
+            Some("Bearer token")
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_connect_redirect_relative_urls() {
+        use super::{ClientBuilder, Credentials};
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start_with_session(
+            |session| session["apiUrl"] = "api/".into(),
+            |_| TestResponse::new(307, "").header("Location", "/.well-known/jmap"),
+        );
+
+        let client = block_on(
+            ClientBuilder::new()
+                .credentials(Credentials::bearer("token"))
+                .follow_redirects(["127.0.0.1"])
+                .connect(&format!("{}/old", server.url)),
+        )
+        .unwrap();
+        assert_eq!(
+            client.session().api_url(),
+            format!("{}/.well-known/api/", server.url)
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_connect_with_retry() {
+        use super::{Client, ClientBuilder, Credentials};
+        use crate::{
+            test_server::{block_on, TestResponse, TestServer},
+            Error,
+        };
+        use std::{
+            io::{Read, Write},
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+            time::Duration,
+        };
+
+        // The listener stays bound, the first attempt times out as connections
+        // are only accepted once the server starts.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            TestServer::start_on(listener, |_| TestResponse::new(500, ""))
+        });
+
+        let client = block_on(
+            ClientBuilder::new()
+                .credentials(Credentials::bearer("token"))
+                .timeout(Duration::from_millis(100))
+                .connect_with_retry(&url, 10, Duration::from_millis(50)),
+        )
+        .unwrap();
+        assert_eq!(client.session().username(), "jdoe@example.org");
+        assert!(!server.join().unwrap().session_requests().is_empty());
+
+        assert!(matches!(
+            block_on(Client::connect_with_retry(
+                "http://127.0.0.1:1",
+                Credentials::bearer("token"),
+                1,
+                Duration::from_millis(200),
+            )),
+            Err(Error::Transport(_))
+        ));
+
+        // Authentication failures are not retried.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let connections_ = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                connections_.fetch_add(1, Ordering::Relaxed);
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request);
+                let _ = stream.write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n");
+            }
+        });
+        assert!(matches!(
+            block_on(Client::connect_with_retry(
+                &url,
+                Credentials::bearer("wrong"),
+                5,
+                Duration::from_millis(10),
+            )),
+            Err(Error::Server { status: 401, .. })
+        ));
+        assert_eq!(connections.load(Ordering::Relaxed), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_connect_from_env() {
+        use super::{env_settings, Client, Credentials};
+
+        // Covers connect_from_env through env_settings and ClientBuilder::connect
+        // without modifying the environment, which other tests read concurrently.
+        use crate::test_server::{block_on, TestResponse, TestServer};
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#)
+        });
+        let (url, credentials) = env_settings(|name| match name {
+            "JMAP_URL" => Some(server.url.clone()),
+            "JMAP_USER" => Some("jdoe@example.org".to_string()),
+            "JMAP_PASSWORD" => Some("secret".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        block_on(Client::new().credentials(credentials).connect(&url)).unwrap();
+        assert_eq!(
+            server.session_requests()[0].header("Authorization"),
+            Some("Basic amRvZUBleGFtcGxlLm9yZzpzZWNyZXQ=")
+        );
+
+        const VARS: &[(&str, &str)] = &[
+            ("JMAP_URL", "https://jmap.example.org"),
+            ("JMAP_TOKEN", "token"),
+        ];
+        let env = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(
+            env_settings(env(VARS)).unwrap(),
+            (
+                "https://jmap.example.org".to_string(),
+                Credentials::bearer("token")
+            )
+        );
+        assert_eq!(
+            env_settings(env(&[("JMAP_PASSWORD", "secret")]))
+                .unwrap_err()
+                .to_string(),
+            "Internal error: Missing environment variables: JMAP_URL, JMAP_TOKEN or JMAP_USER"
+        );
+        assert_eq!(
+            env_settings(env(&[("JMAP_URL", "https://jmap.example.org")]))
+                .unwrap_err()
+                .to_string(),
+            "Internal error: Missing environment variables: JMAP_TOKEN or JMAP_USER and JMAP_PASSWORD"
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_circuit_breaker() {
+        use crate::{
+            test_server::{block_on, TestResponse, TestServer},
+            Error,
+        };
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            time::Duration,
+        };
+
+        let calls = AtomicUsize::new(0);
+        let server = TestServer::start(move |_| {
+            if calls.fetch_add(1, Ordering::Relaxed) < 3 {
+                TestResponse::new(500, "")
+            } else {
+                TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#)
+            }
+        });
+        let request = serde_json::json!({
+            "using": ["urn:ietf:params:jmap:core"],
+            "methodCalls": [],
+        });
+
+        block_on(async {
+            let mut client = server.connect().await;
+            client.enable_circuit_breaker(3, Duration::from_millis(100));
+            for _ in 0..3 {
+                assert!(matches!(
+                    client.send::<serde_json::Value>(&request).await,
+                    Err(Error::Server { status: 500, .. })
+                ));
+            }
+            assert!(matches!(
+                client.send::<serde_json::Value>(&request).await,
+                Err(Error::CircuitOpen)
+            ));
+            assert_eq!(server.requests().len(), 3);
+
+            std::thread::sleep(Duration::from_millis(150));
+            client.send::<serde_json::Value>(&request).await.unwrap();
+            client.send::<serde_json::Value>(&request).await.unwrap();
+            assert_eq!(server.requests().len(), 5);
+        });
+    }
 }
@@ -9,7 +9,11 @@
  * except according to those terms.
  */
 
-use std::{pin::Pin, sync::Arc};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
 
 use ahash::AHashMap;
 use futures_util::{stream::SplitSink, SinkExt, Stream, StreamExt};
@@ -161,11 +165,23 @@ pub enum WebSocketMessage {
     StateChange(Changes),
 }
 
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+
 pub struct WsStream {
-    tx: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+    tx: Arc<tokio::sync::Mutex<WsSink>>,
     req_id: usize,
 }
 
+/// Stream of WebSocket messages returned by [`Client::connect_ws`].
+///
+/// Since `Drop` cannot be async, dropping the guard spawns a detached task on
+/// the current Tokio runtime that sends a close frame on a best-effort basis.
+/// Use [`WsGuard::close`] to shut the connection down gracefully.
+pub struct WsGuard {
+    stream: Pin<Box<dyn Stream<Item = crate::Result<WebSocketMessage>> + Send>>,
+    tx: Option<Arc<tokio::sync::Mutex<WsSink>>>,
+}
+
 #[doc(hidden)]
 #[derive(Debug)]
 struct DummyVerifier;
@@ -220,9 +236,7 @@ impl ServerCertVerifier for DummyVerifier {
 }
 
 impl Client {
-    pub async fn connect_ws(
-        &self,
-    ) -> crate::Result<Pin<Box<impl Stream<Item = crate::Result<WebSocketMessage>>>>> {
+    pub async fn connect_ws(&self) -> crate::Result<WsGuard> {
         let session = self.session();
         let capabilities = session.websocket_capabilities().ok_or_else(|| {
             crate::Error::Internal(
@@ -252,40 +266,10 @@ impl Client {
         } else {
             tokio_tungstenite::connect_async(request).await?
         };
-        let (tx, mut rx) = stream.split();
+        let (ws, guard) = WsGuard::new(stream);
+        *self.ws.lock().await = ws.into();
 
-        *self.ws.lock().await = WsStream { tx, req_id: 0 }.into();
-
-        Ok(Box::pin(async_stream::stream! {
-            while let Some(message) = rx.next().await {
-                match message {
-                    Ok(message) if message.is_text() => {
-                        match serde_json::from_slice::<WebSocketMessage_>(&message.into_data()) {
-                            Ok(message) => match message {
-                                WebSocketMessage_::Response(response) => {
-                                    yield Ok(WebSocketMessage::Response(Response::new(
-                                        response.method_responses,
-                                        response.created_ids,
-                                        response.session_state,
-                                        response.request_id,
-                                    )))
-                                }
-                                WebSocketMessage_::StateChange(changes) => {
-                                    yield Ok(WebSocketMessage::StateChange(Changes::new(
-                                        changes.push_state,
-                                        changes.changed,
-                                    )))
-                                }
-                                WebSocketMessage_::Error(err) => yield Err(ProblemDetails::from(err).into()),
-                            },
-                            Err(err) => yield Err(err.into()),
-                        }
-                    }
-                    Ok(_) => (),
-                    Err(err) => yield Err(err.into()),
-                }
-            }
-        }))
+        Ok(guard)
     }
 
     pub async fn send_ws(&self, request: Request<'_>) -> crate::Result<String> {
@@ -299,6 +283,8 @@ impl Client {
         ws.req_id += 1;
 
         ws.tx
+            .lock()
+            .await
             .send(Message::text(
                 serde_json::to_string(&WebSocketRequest {
                     _type: WebSocketRequestType::Request,
@@ -325,6 +311,8 @@ impl Client {
             .as_mut()
             .ok_or_else(|| crate::Error::Internal("Websocket stream not set.".to_string()))?
             .tx
+            .lock()
+            .await
             .send(Message::text(
                 serde_json::to_string(&WebSocketPushEnable {
                     _type: WebSocketPushEnableType::WebSocketPushEnable,
@@ -344,6 +332,8 @@ impl Client {
             .as_mut()
             .ok_or_else(|| crate::Error::Internal("Websocket stream not set.".to_string()))?
             .tx
+            .lock()
+            .await
             .send(Message::text(
                 serde_json::to_string(&WebSocketPushDisable {
                     _type: WebSocketPushDisableType::WebSocketPushDisable,
@@ -361,12 +351,86 @@ impl Client {
             .as_mut()
             .ok_or_else(|| crate::Error::Internal("Websocket stream not set.".to_string()))?
             .tx
+            .lock()
+            .await
             .send(Message::Ping(vec![]))
             .await
             .map_err(|err| err.into())
     }
 }
 
+impl WsGuard {
+    fn new(stream: WebSocketStream<MaybeTlsStream<TcpStream>>) -> (WsStream, WsGuard) {
+        let (tx, mut rx) = stream.split();
+        let tx = Arc::new(tokio::sync::Mutex::new(tx));
+
+        (
+            WsStream {
+                tx: tx.clone(),
+                req_id: 0,
+            },
+            WsGuard {
+                stream: Box::pin(async_stream::stream! {
+                    while let Some(message) = rx.next().await {
+                        match message {
+                            Ok(message) if message.is_text() => {
+                                match serde_json::from_slice::<WebSocketMessage_>(&message.into_data()) {
+                                    Ok(message) => match message {
+                                        WebSocketMessage_::Response(response) => {
+                                            yield Ok(WebSocketMessage::Response(Response::new(
+                                                response.method_responses,
+                                                response.created_ids,
+                                                response.session_state,
+                                                response.request_id,
+                                            )))
+                                        }
+                                        WebSocketMessage_::StateChange(changes) => {
+                                            yield Ok(WebSocketMessage::StateChange(Changes::new(
+                                                changes.push_state,
+                                                changes.changed,
+                                            )))
+                                        }
+                                        WebSocketMessage_::Error(err) => yield Err(ProblemDetails::from(err).into()),
+                                    },
+                                    Err(err) => yield Err(err.into()),
+                                }
+                            }
+                            Ok(_) => (),
+                            Err(err) => yield Err(err.into()),
+                        }
+                    }
+                }),
+                tx: tx.into(),
+            },
+        )
+    }
+
+    pub async fn close(mut self) -> crate::Result<()> {
+        if let Some(tx) = self.tx.take() {
+            tx.lock().await.close().await?;
+        }
+        Ok(())
+    }
+}
+
+impl Stream for WsGuard {
+    type Item = crate::Result<WebSocketMessage>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.as_mut().poll_next(cx)
+    }
+}
+
+impl Drop for WsGuard {
+    fn drop(&mut self) {
+        if let (Some(tx), Ok(handle)) = (self.tx.take(), tokio::runtime::Handle::try_current()) {
+            handle.spawn(async move {
+                let _ = tx.lock().await.close().await;
+            });
+        }
+    }
+}
+
 impl From<WebSocketError> for ProblemDetails {
     fn from(problem: WebSocketError) -> Self {
         ProblemDetails::new(
@@ -379,3 +443,47 @@ impl From<WebSocketError> for ProblemDetails {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::WsGuard;
+
+    #[test]
+    fn close_on_drop() {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let addr = listener.local_addr().unwrap();
+                let server = tokio::spawn(async move {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(message) = ws.next().await {
+                        if let Ok(Message::Close(_)) = message {
+                            return true;
+                        }
+                    }
+                    false
+                });
+
+                let (stream, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+                    .await
+                    .unwrap();
+                let (_ws, guard) = WsGuard::new(stream);
+                drop(guard);
+
+                assert!(
+                    tokio::time::timeout(std::time::Duration::from_secs(5), server)
+                        .await
+                        .unwrap()
+                        .unwrap()
+                );
+            });
+    }
+}
@@ -0,0 +1,76 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+//! Base64 helpers shared by the rest of the crate.
+
+/// Encodes `input` using the standard alphabet with padding, as required by
+/// HTTP Basic authentication.
+pub fn encode(input: impl AsRef<[u8]>) -> String {
+    ::base64::encode_config(input, ::base64::STANDARD)
+}
+
+/// Encodes `input` using the URL and filename safe alphabet with padding.
+pub fn encode_url_safe(input: impl AsRef<[u8]>) -> String {
+    ::base64::encode_config(input, ::base64::URL_SAFE)
+}
+
+/// Decodes `input` written in either the standard or the URL safe alphabet,
+/// with or without trailing padding.
+pub fn decode(input: impl AsRef<[u8]>) -> Option<Vec<u8>> {
+    let mut input = input
+        .as_ref()
+        .iter()
+        .filter(|ch| !ch.is_ascii_whitespace())
+        .map(|ch| match ch {
+            b'-' => b'+',
+            b'_' => b'/',
+            ch => *ch,
+        })
+        .collect::<Vec<_>>();
+    for _ in 0..2 {
+        if input.last() == Some(&b'=') {
+            input.pop();
+        }
+    }
+    ::base64::decode_config(input, ::base64::STANDARD_NO_PAD).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::Credentials;
+
+    #[test]
+    fn basic_credentials() {
+        match Credentials::basic("jdoe@example.org", "p?ss>word") {
+            Credentials::Basic(value) => {
+                assert_eq!(value, "amRvZUBleGFtcGxlLm9yZzpwP3NzPndvcmQ=");
+                assert_eq!(
+                    super::decode(&value).unwrap(),
+                    b"jdoe@example.org:p?ss>word"
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn decode_url_safe() {
+        let data = [0xfb, 0xff, 0xfe, 0x3e, 0x3f, 0x00];
+        assert_eq!(super::encode_url_safe(data), "-__-Pj8A");
+        assert_eq!(super::decode("-__-Pj8A").unwrap(), data);
+        assert_eq!(super::decode("+//+Pj8A").unwrap(), data);
+        assert_eq!(super::decode("-__-Pj8").unwrap(), &data[..5]);
+        assert_eq!(super::decode("-__-Pj8=").unwrap(), &data[..5]);
+        assert_eq!(super::decode("-__-P!8A"), None);
+        assert_eq!(super::decode("-__=-Pj8"), None);
+        assert_eq!(super::decode("ab=cd"), None);
+    }
+}
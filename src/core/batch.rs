@@ -0,0 +1,335 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use crate::{client::Client, Error};
+
+use super::{request::Request, response::TaggedMethodResponse};
+
+/// Accumulates method calls, sending them whenever the server's
+/// `maxCallsInRequest` limit is reached.
+///
+/// Since queued calls may end up in different requests, method calls should
+/// not use result references or creation ids pointing to other calls. Call ids
+/// are unique within the batch, see [`Batch::last_call_id`].
+pub struct Batch<'x> {
+    client: &'x Client,
+    request: Request<'x>,
+    max_calls: usize,
+    next_call_id: usize,
+    last_call_id: Option<String>,
+    responses: Vec<TaggedMethodResponse>,
+}
+
+impl Client {
+    pub fn batch(&self) -> Batch<'_> {
+        Batch {
+            client: self,
+            request: self.build(),
+            max_calls: self
+                .session()
+                .core_capabilities()
+                .map(|capabilities| capabilities.max_calls_in_request())
+                .unwrap_or(usize::MAX)
+                .max(1),
+            next_call_id: 0,
+            last_call_id: None,
+            responses: Vec::new(),
+        }
+    }
+}
+
+impl<'x> Batch<'x> {
+    /// Queues the method calls added by `f`, first sending the pending calls
+    /// if the new ones would not fit within the limit. Fails if `f` alone adds
+    /// more calls than the server accepts in a single request.
+    #[maybe_async::maybe_async]
+    pub async fn add(&mut self, f: impl FnOnce(&mut Request<'x>)) -> crate::Result<()> {
+        let mut request = self.client.build();
+        f(&mut request);
+        if request.len() > self.max_calls {
+            return Err(Error::Internal(format!(
+                "{} method calls exceed the server limit of {} calls per request.",
+                request.len(),
+                self.max_calls
+            )));
+        }
+        if self.request.len() + request.len() > self.max_calls {
+            self.flush().await?;
+        }
+
+        for uri in request.using {
+            self.request.add_capability(uri);
+        }
+        if let Some(created_ids) = request.created_ids {
+            self.request
+                .created_ids
+                .get_or_insert_with(Default::default)
+                .extend(created_ids);
+        }
+        for (method, arguments, _) in request.method_calls {
+            let call_id = format!("s{}", self.next_call_id);
+            self.next_call_id += 1;
+            self.last_call_id = Some(call_id.clone());
+            self.request.method_calls.push((method, arguments, call_id));
+        }
+        if self.request.len() >= self.max_calls {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Sends any pending method calls.
+    #[maybe_async::maybe_async]
+    pub async fn flush(&mut self) -> crate::Result<()> {
+        if !self.request.is_empty() {
+            let request = std::mem::replace(&mut self.request, self.client.build());
+            self.responses
+                .extend(request.send().await?.unwrap_method_responses());
+        }
+        Ok(())
+    }
+
+    /// Number of method calls waiting to be sent.
+    pub fn pending(&self) -> usize {
+        self.request.len()
+    }
+
+    pub fn responses(&self) -> &[TaggedMethodResponse] {
+        &self.responses
+    }
+
+    pub fn take_responses(&mut self) -> Vec<TaggedMethodResponse> {
+        std::mem::take(&mut self.responses)
+    }
+
+    /// Returns the call id of the last queued method call, which may already
+    /// have been sent.
+    pub fn last_call_id(&self) -> Option<&str> {
+        self.last_call_id.as_deref()
+    }
+
+    pub fn take_response_by_id(&mut self, id: &str) -> Option<TaggedMethodResponse> {
+        self.responses
+            .iter()
+            .position(|response| response.call_id() == id)
+            .map(|pos| self.responses.remove(pos))
+    }
+}
+
+/// Outcome of an operation split into several requests, such as
+/// [`Client::email_apply_patch_chunked`].
+#[derive(Debug, Default)]
+pub struct ChunkResults {
+    chunks: Vec<ChunkResult>,
+}
+
+#[derive(Debug)]
+pub struct ChunkResult {
+    ids: Vec<String>,
+    result: crate::Result<()>,
+}
+
+impl ChunkResults {
+    pub(crate) fn push(&mut self, ids: &[String], result: crate::Result<()>) {
+        self.chunks.push(ChunkResult {
+            ids: ids.to_vec(),
+            result,
+        });
+    }
+
+    pub fn chunks(&self) -> &[ChunkResult] {
+        &self.chunks
+    }
+
+    /// Returns `true` if all the chunks succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.chunks.iter().all(|chunk| chunk.result.is_ok())
+    }
+
+    pub fn succeeded_ids(&self) -> impl Iterator<Item = &String> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.result.is_ok())
+            .flat_map(|chunk| chunk.ids.iter())
+    }
+
+    pub fn failed_ids(&self) -> impl Iterator<Item = &String> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.result.is_err())
+            .flat_map(|chunk| chunk.ids.iter())
+    }
+
+    /// Returns the error of the first failed chunk, if any.
+    pub fn into_result(self) -> crate::Result<()> {
+        self.chunks
+            .into_iter()
+            .map(|chunk| chunk.result)
+            .find(|result| result.is_err())
+            .unwrap_or(Ok(()))
+    }
+}
+
+impl ChunkResult {
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    pub fn result(&self) -> &crate::Result<()> {
+        &self.result
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::test_server::{block_on, TestResponse, TestServer};
+
+    #[test]
+    fn batch_flushes_at_limit() {
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:core"]["maxCallsInRequest"] = 3.into()
+            },
+            |request| {
+                let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let responses = request["methodCalls"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|call| {
+                        serde_json::json!([
+                            "Email/set",
+                            {"accountId": "a", "newState": "e2"},
+                            call[2]
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                TestResponse::json(
+                    200,
+                    serde_json::json!({"sessionState": "s1", "methodResponses": responses})
+                        .to_string(),
+                )
+            },
+        );
+
+        block_on(async {
+            let client = server.connect().await;
+            let mut batch = client.batch();
+            for id in 0..5 {
+                batch
+                    .add(|request| {
+                        request
+                            .set_email()
+                            .update(format!("m{}", id))
+                            .keyword("$seen", true);
+                    })
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(server.requests().len(), 1);
+            assert_eq!(batch.pending(), 2);
+
+            batch.flush().await.unwrap();
+            assert_eq!(batch.pending(), 0);
+            assert_eq!(batch.take_responses().len(), 5);
+        });
+
+        let calls = server
+            .requests()
+            .iter()
+            .map(|request| {
+                serde_json::from_slice::<serde_json::Value>(&request.body).unwrap()["methodCalls"]
+                    .as_array()
+                    .unwrap()
+                    .len()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(calls, [3, 2]);
+    }
+
+    #[test]
+    fn batch_keeps_multi_call_closures_within_limit() {
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:core"]["maxCallsInRequest"] = 3.into()
+            },
+            |request| {
+                let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let responses = request["methodCalls"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|call| {
+                        serde_json::json!([
+                            "Email/set",
+                            {"accountId": "a", "newState": "e2"},
+                            call[2]
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                TestResponse::json(
+                    200,
+                    serde_json::json!({"sessionState": "s1", "methodResponses": responses})
+                        .to_string(),
+                )
+            },
+        );
+
+        block_on(async {
+            let client = server.connect().await;
+            let mut batch = client.batch();
+            for calls in [1, 1, 2, 3] {
+                batch
+                    .add(|request| {
+                        for id in 0..calls {
+                            request
+                                .set_email()
+                                .update(format!("m{}", id))
+                                .keyword("$seen", true);
+                        }
+                    })
+                    .await
+                    .unwrap();
+            }
+            assert!(batch
+                .add(|request| {
+                    for id in 0..4 {
+                        request.set_email().destroy([format!("m{}", id)]);
+                    }
+                })
+                .await
+                .is_err());
+            batch.flush().await.unwrap();
+            assert_eq!(batch.take_responses().len(), 7);
+        });
+
+        let calls = server
+            .requests()
+            .iter()
+            .map(|request| {
+                serde_json::from_slice::<serde_json::Value>(&request.body).unwrap()["methodCalls"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|call| call[2].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            calls,
+            [vec!["s0", "s1"], vec!["s2", "s3"], vec!["s4", "s5", "s6"]]
+        );
+    }
+}
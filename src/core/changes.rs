@@ -9,11 +9,12 @@
  * except according to those terms.
  */
 
+use ahash::AHashMap;
 use serde::{Deserialize, Serialize};
 
-use crate::Method;
+use crate::{Method, TypeState};
 
-use super::{request::ResultReference, Object, RequestParams};
+use super::{request::ResultReference, AccountId, Object, RequestParams};
 
 pub trait ChangesObject: Object {
     type ChangesResponse;
@@ -25,7 +26,7 @@ pub struct ChangesRequest {
     method: (Method, usize),
 
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
 
     #[serde(rename = "sinceState")]
     since_state: String,
@@ -69,7 +70,7 @@ impl ChangesRequest {
         }
     }
 
-    pub fn account_id(&mut self, account_id: impl Into<String>) -> &mut Self {
+    pub fn account_id(&mut self, account_id: impl Into<AccountId>) -> &mut Self {
         self.account_id = account_id.into();
         self
     }
@@ -125,6 +126,7 @@ impl<O: ChangesObject> ChangesResponse<O> {
         std::mem::take(&mut self.created)
     }
 
+    /// Ids of the updated objects, in the order returned by the server.
     pub fn updated(&self) -> &[String] {
         &self.updated
     }
@@ -145,7 +147,200 @@ impl<O: ChangesObject> ChangesResponse<O> {
         &self.arguments
     }
 
+    /// Moves the ids that a later `get` call reported as `notFound` from the
+    /// created and updated lists to the destroyed list, since they were
+    /// destroyed after the changes were computed. Returns `true` if any id
+    /// was moved.
+    pub fn reconcile_not_found(&mut self, not_found: &[String]) -> bool {
+        let total = self.destroyed.len();
+        for ids in [&mut self.created, &mut self.updated] {
+            ids.retain(|id| {
+                if not_found.contains(id) {
+                    if !self.destroyed.contains(id) {
+                        self.destroyed.push(id.clone());
+                    }
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        self.destroyed.len() != total
+    }
+
     pub fn total_changes(&self) -> usize {
         self.created.len() + self.updated.len() + self.destroyed.len()
     }
 }
+
+/// Sync cursors known to a client, which applications can persist with
+/// [`Client::export_sync_state`](crate::client::Client::export_sync_state)
+/// and restore with
+/// [`Client::import_sync_state`](crate::client::Client::import_sync_state).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    #[serde(rename = "states")]
+    #[serde(default)]
+    states: AHashMap<TypeState, String>,
+
+    #[serde(rename = "queryStates")]
+    #[serde(default)]
+    query_states: AHashMap<String, String>,
+}
+
+impl SyncState {
+    pub fn state(&self, type_: &TypeState) -> Option<&str> {
+        self.states.get(type_).map(|state| state.as_str())
+    }
+
+    pub fn set_state(&mut self, type_: TypeState, state: impl Into<String>) -> &mut Self {
+        self.states.insert(type_, state.into());
+        self
+    }
+
+    /// State of the query saved under `name`, as chosen by the application.
+    pub fn query_state(&self, name: &str) -> Option<&str> {
+        self.query_states.get(name).map(|state| state.as_str())
+    }
+
+    pub fn set_query_state(
+        &mut self,
+        name: impl Into<String>,
+        state: impl Into<String>,
+    ) -> &mut Self {
+        self.query_states.insert(name.into(), state.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty() && self.query_states.is_empty()
+    }
+}
+
+/// Changes accumulated over consecutive `changes` calls, such as those made
+/// by [`Client::email_sync_changes`](crate::client::Client::email_sync_changes).
+#[derive(Debug, Clone, Default)]
+pub struct SyncChanges {
+    new_state: String,
+    created: Vec<String>,
+    updated: Vec<String>,
+    destroyed: Vec<String>,
+    has_more: bool,
+    full_refresh: bool,
+    iterations: usize,
+}
+
+impl SyncChanges {
+    pub(crate) fn new(since_state: String) -> Self {
+        SyncChanges {
+            new_state: since_state,
+            ..Default::default()
+        }
+    }
+
+    /// Merges the next page of changes. Objects created and then destroyed
+    /// within the sync are dropped, and objects created and then updated are
+    /// only reported as created.
+    pub(crate) fn merge<O: ChangesObject>(&mut self, mut response: ChangesResponse<O>) {
+        for id in response.take_created() {
+            if !self.created.contains(&id) {
+                self.created.push(id);
+            }
+        }
+        for id in response.take_updated() {
+            if !self.created.contains(&id) && !self.updated.contains(&id) {
+                self.updated.push(id);
+            }
+        }
+        for id in response.take_destroyed() {
+            if let Some(pos) = self.created.iter().position(|c| c == &id) {
+                self.created.remove(pos);
+            } else {
+                self.updated.retain(|u| u != &id);
+                if !self.destroyed.contains(&id) {
+                    self.destroyed.push(id);
+                }
+            }
+        }
+        self.has_more = response.has_more_changes();
+        self.new_state = response.take_new_state();
+        self.iterations += 1;
+    }
+
+    /// Discards the changes merged so far, as the server could not calculate
+    /// them, and resumes from `new_state` once all the objects are fetched again.
+    pub(crate) fn full_refresh(&mut self, new_state: String) {
+        self.created.clear();
+        self.updated.clear();
+        self.destroyed.clear();
+        self.has_more = false;
+        self.full_refresh = true;
+        self.new_state = new_state;
+        self.iterations += 1;
+    }
+
+    /// State to pass as `sinceState` to resume syncing.
+    pub fn new_state(&self) -> &str {
+        &self.new_state
+    }
+
+    pub fn created(&self) -> &[String] {
+        &self.created
+    }
+
+    pub fn updated(&self) -> &[String] {
+        &self.updated
+    }
+
+    pub fn destroyed(&self) -> &[String] {
+        &self.destroyed
+    }
+
+    /// Returns `true` if the sync stopped before all the changes were
+    /// fetched, in which case it should be resumed from [`SyncChanges::new_state`].
+    pub fn has_more(&self) -> bool {
+        self.has_more
+    }
+
+    /// Returns `true` if the server could not calculate the changes, in which
+    /// case no changes are reported and all the objects must be fetched again
+    /// before resuming from [`SyncChanges::new_state`].
+    pub fn is_full_refresh(&self) -> bool {
+        self.full_refresh
+    }
+
+    /// Number of `changes` calls made.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn total_changes(&self) -> usize {
+        self.created.len() + self.updated.len() + self.destroyed.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::response::EmailChangesResponse;
+
+    #[test]
+    fn updated_order_preserved() {
+        let mut changes: EmailChangesResponse = serde_json::from_str(
+            r#"{
+                "accountId": "a",
+                "oldState": "e1",
+                "newState": "e2",
+                "hasMoreChanges": false,
+                "created": [],
+                "updated": ["m9", "m2", "m5", "m1"],
+                "destroyed": ["m3"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(changes.updated(), ["m9", "m2", "m5", "m1"]);
+
+        changes.reconcile_not_found(&["m2".to_string()]);
+        assert_eq!(changes.take_updated(), ["m9", "m5", "m1"]);
+        assert_eq!(changes.destroyed(), ["m3", "m2"]);
+    }
+}
@@ -16,20 +16,20 @@ use crate::Error;
 
 use super::{
     set::{SetError, SetObject},
-    RequestParams,
+    AccountId, RequestParams,
 };
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CopyRequest<O: SetObject> {
     #[serde(rename = "fromAccountId")]
-    from_account_id: String,
+    from_account_id: AccountId,
 
     #[serde(rename = "ifFromInState")]
     #[serde(skip_serializing_if = "Option::is_none")]
     if_from_in_state: Option<String>,
 
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
 
     #[serde(rename = "ifInState")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -68,7 +68,7 @@ pub struct CopyResponse<O: SetObject> {
 }
 
 impl<T: SetObject> CopyRequest<T> {
-    pub fn new(params: RequestParams, from_account_id: String) -> Self {
+    pub fn new(params: RequestParams, from_account_id: AccountId) -> Self {
         CopyRequest {
             from_account_id,
             if_from_in_state: None,
@@ -80,7 +80,7 @@ impl<T: SetObject> CopyRequest<T> {
         }
     }
 
-    pub fn account_id(&mut self, account_id: impl Into<String>) -> &mut Self {
+    pub fn account_id(&mut self, account_id: impl Into<AccountId>) -> &mut Self {
         self.account_id = account_id.into();
         self
     }
@@ -47,6 +47,8 @@ pub enum ProblemType {
 pub struct MethodError {
     #[serde(rename = "type")]
     pub p_type: MethodErrorType,
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Eq)]
@@ -91,6 +93,8 @@ pub enum MethodErrorType {
     UnsupportedFilter,
     #[serde(rename = "tooManyChanges")]
     TooManyChanges,
+    #[serde(other)]
+    Unknown,
 }
 
 impl ProblemDetails {
@@ -141,6 +145,17 @@ impl MethodError {
     pub fn error(&self) -> &MethodErrorType {
         &self.p_type
     }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub(crate) fn append_description(&mut self, text: &str) {
+        self.description = Some(match self.description.take() {
+            Some(description) => format!("{} ({})", description, text),
+            None => text.to_string(),
+        });
+    }
 }
 
 impl Display for MethodError {
@@ -170,7 +185,12 @@ impl Display for MethodError {
             MethodErrorType::UnsupportedSort => write!(f, "Unsupported sort"),
             MethodErrorType::UnsupportedFilter => write!(f, "Unsupported filter"),
             MethodErrorType::TooManyChanges => write!(f, "Too many changes"),
+            MethodErrorType::Unknown => write!(f, "Unknown error"),
+        }?;
+        if let Some(description) = &self.description {
+            write!(f, ": {}", description)?;
         }
+        Ok(())
     }
 }
 
@@ -181,7 +201,10 @@ impl Display for ProblemDetails {
                 JMAPError::UnknownCapability => write!(f, "Unknown capability")?,
                 JMAPError::NotJSON => write!(f, "Not JSON")?,
                 JMAPError::NotRequest => write!(f, "Not request")?,
-                JMAPError::Limit => write!(f, "Limit")?,
+                JMAPError::Limit => match &self.limit {
+                    Some(limit) => write!(f, "Limit {}", limit)?,
+                    None => write!(f, "Limit")?,
+                },
             },
             ProblemType::Other(err) => f.write_str(err.as_str())?,
         }
@@ -9,11 +9,12 @@
  * except according to those terms.
  */
 
+use ahash::AHashMap;
 use serde::{Deserialize, Serialize};
 
-use crate::Method;
+use crate::{client::Client, Error, Method, TypeState};
 
-use super::{request::ResultReference, Object, RequestParams};
+use super::{request::ResultReference, AccountId, Object, RequestParams};
 
 pub trait GetObject: Object {
     type GetArguments: Default;
@@ -26,7 +27,7 @@ pub struct GetRequest<O: GetObject> {
 
     #[serde(rename = "accountId")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    account_id: Option<String>,
+    account_id: Option<AccountId>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     ids: Option<Vec<String>>,
@@ -78,7 +79,7 @@ impl<O: GetObject> GetRequest<O> {
         }
     }
 
-    pub fn account_id(&mut self, account_id: impl Into<String>) -> &mut Self {
+    pub fn account_id(&mut self, account_id: impl Into<AccountId>) -> &mut Self {
         if O::requires_account_id() {
             self.account_id = Some(account_id.into());
         }
@@ -158,3 +159,131 @@ impl<O> GetResponse<O> {
         std::mem::take(&mut self.not_found)
     }
 }
+
+impl Client {
+    /// Fetches the current state of each of the given types, for example to
+    /// seed `changes` calls on startup. A `get` call with an empty list of ids
+    /// is sent for each type, all in a single request. `EmailDelivery` has no
+    /// `get` method and is rejected.
+    #[maybe_async::maybe_async]
+    pub async fn current_states(
+        &self,
+        types: impl IntoIterator<Item = TypeState>,
+    ) -> crate::Result<AHashMap<TypeState, String>> {
+        let mut request = self.build();
+        let mut requested: Vec<(TypeState, String)> = Vec::new();
+        for type_ in types {
+            if requested.iter().any(|(requested, _)| requested == &type_) {
+                continue;
+            }
+            let no_ids = Vec::<String>::new();
+            match type_ {
+                TypeState::Mailbox => {
+                    request.get_mailbox().ids(no_ids);
+                }
+                TypeState::Thread => {
+                    request.get_thread().ids(no_ids);
+                }
+                TypeState::Email => {
+                    request.get_email().ids(no_ids);
+                }
+                TypeState::Identity => {
+                    request.get_identity().ids(no_ids);
+                }
+                TypeState::EmailSubmission => {
+                    request.get_email_submission().ids(no_ids);
+                }
+                TypeState::EmailDelivery => {
+                    return Err(Error::from("EmailDelivery has no get method"));
+                }
+            }
+            requested.push((type_, request.last_call_id().unwrap().to_string()));
+        }
+
+        let mut states = AHashMap::with_capacity(requested.len());
+        if requested.is_empty() {
+            return Ok(states);
+        }
+        let mut response = request.send().await?;
+        for (type_, call_id) in requested {
+            let response = response
+                .take_method_response_by_id(&call_id)
+                .ok_or_else(|| Error::from("Server returned no results"))?;
+            let state = match type_ {
+                TypeState::Mailbox => response.unwrap_get_mailbox()?.take_state(),
+                TypeState::Thread => response.unwrap_get_thread()?.take_state(),
+                TypeState::Email => response.unwrap_get_email()?.take_state(),
+                TypeState::Identity => response.unwrap_get_identity()?.take_state(),
+                TypeState::EmailSubmission => response.unwrap_get_email_submission()?.take_state(),
+                TypeState::EmailDelivery => unreachable!(),
+            };
+            states.insert(type_, state);
+        }
+        Ok(states)
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::{
+        test_server::{block_on, TestResponse, TestServer},
+        TypeState,
+    };
+
+    #[test]
+    fn current_states() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            // Responses are returned in reverse order, matched by call id.
+            let responses = request["methodCalls"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .rev()
+                .map(|call| {
+                    assert_eq!(call[1]["ids"], serde_json::json!([]));
+                    let name = call[0].as_str().unwrap();
+                    serde_json::json!([
+                        name,
+                        {
+                            "accountId": "a",
+                            "state": format!("{}-state", name.split('/').next().unwrap()),
+                            "list": [],
+                            "notFound": []
+                        },
+                        call[2]
+                    ])
+                })
+                .collect::<Vec<_>>();
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": responses}).to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let states = client
+                .current_states([
+                    TypeState::Email,
+                    TypeState::Mailbox,
+                    TypeState::Thread,
+                    TypeState::EmailSubmission,
+                    TypeState::Email,
+                ])
+                .await
+                .unwrap();
+            assert_eq!(states.len(), 4);
+            assert_eq!(states[&TypeState::Email], "Email-state");
+            assert_eq!(states[&TypeState::Mailbox], "Mailbox-state");
+            assert_eq!(states[&TypeState::Thread], "Thread-state");
+            assert_eq!(states[&TypeState::EmailSubmission], "EmailSubmission-state");
+
+            assert!(client
+                .current_states([TypeState::EmailDelivery])
+                .await
+                .is_err());
+        });
+        assert_eq!(server.requests().len(), 1);
+    }
+}
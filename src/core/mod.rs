@@ -9,12 +9,14 @@
  * except according to those terms.
  */
 
-use std::fmt::Display;
+use std::{fmt::Display, ops::Deref};
 
 use serde::{Deserialize, Serialize};
 
 use crate::Method;
 
+pub mod base64;
+pub mod batch;
 pub mod changes;
 pub mod copy;
 pub mod error;
@@ -26,14 +28,19 @@ pub mod response;
 pub mod session;
 pub mod set;
 
+/// Identifier of a JMAP account, kept distinct from object ids.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AccountId(String);
+
 pub struct RequestParams {
-    pub account_id: String,
+    pub account_id: AccountId,
     pub method: Method,
     pub call_id: usize,
 }
 
 impl RequestParams {
-    pub fn new(account_id: impl Into<String>, method: Method, call_id: usize) -> Self {
+    pub fn new(account_id: impl Into<AccountId>, method: Method, call_id: usize) -> Self {
         Self {
             account_id: account_id.into(),
             method,
@@ -42,6 +49,70 @@ impl RequestParams {
     }
 }
 
+impl AccountId {
+    pub fn new(account_id: impl Into<String>) -> Self {
+        AccountId(account_id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for AccountId {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<str> for AccountId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for AccountId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for AccountId {
+    fn from(account_id: String) -> Self {
+        AccountId(account_id)
+    }
+}
+
+impl From<&String> for AccountId {
+    fn from(account_id: &String) -> Self {
+        AccountId(account_id.clone())
+    }
+}
+
+impl From<&str> for AccountId {
+    fn from(account_id: &str) -> Self {
+        AccountId(account_id.to_string())
+    }
+}
+
+impl From<&AccountId> for AccountId {
+    fn from(account_id: &AccountId) -> Self {
+        account_id.clone()
+    }
+}
+
+impl From<AccountId> for String {
+    fn from(account_id: AccountId) -> Self {
+        account_id.0
+    }
+}
+
 pub trait Object: Sized {
     type Property: Display + Serialize + for<'de> Deserialize<'de>;
     fn requires_account_id() -> bool;
@@ -13,7 +13,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::Method;
 
-use super::{request::ResultReference, Object, RequestParams};
+use super::{request::ResultReference, AccountId, Object, RequestParams};
 
 pub trait QueryObject: Object {
     type QueryArguments: Default + Serialize;
@@ -27,7 +27,7 @@ pub struct QueryRequest<O: QueryObject> {
     method: (Method, usize),
 
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
 
     #[serde(rename = "filter")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -96,6 +96,16 @@ pub struct Comparator<A> {
     arguments: A,
 }
 
+/// A page returned by a query paginator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryPage {
+    /// The ids of the next page of results.
+    Ids(Vec<String>),
+    /// The query state changed between pages, results fetched so far may be
+    /// stale. The paginator restarts from the first page on the next call.
+    StateChanged,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct QueryResponse {
     #[serde(rename = "accountId")]
@@ -136,7 +146,7 @@ impl<O: QueryObject> QueryRequest<O> {
         }
     }
 
-    pub fn account_id(&mut self, account_id: impl Into<String>) -> &mut Self {
+    pub fn account_id(&mut self, account_id: impl Into<AccountId>) -> &mut Self {
         self.account_id = account_id.into();
         self
     }
@@ -183,6 +193,10 @@ impl<O: QueryObject> QueryRequest<O> {
     pub fn result_reference(&self) -> ResultReference {
         ResultReference::new(self.method.0, self.method.1, "/ids")
     }
+
+    pub fn comparators(&self) -> &[Comparator<O::Sort>] {
+        self.sort.as_deref().unwrap_or_default()
+    }
 }
 
 impl QueryResponse {
@@ -255,6 +269,10 @@ impl<A> Comparator<A> {
         self.collation = Some(collation);
         self
     }
+
+    pub fn arguments(&self) -> &A {
+        &self.arguments
+    }
 }
 
 impl<T> From<FilterOperator<T>> for Filter<T> {
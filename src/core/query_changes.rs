@@ -12,14 +12,14 @@
 use serde::{Deserialize, Serialize};
 
 use super::{
-    query::{Comparator, Filter, QueryObject},
-    RequestParams,
+    query::{Comparator, Filter, QueryObject, QueryResponse},
+    AccountId, RequestParams,
 };
 
 #[derive(Debug, Clone, Serialize)]
 pub struct QueryChangesRequest<O: QueryObject> {
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
 
     #[serde(rename = "filter")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -83,7 +83,7 @@ impl<O: QueryObject> QueryChangesRequest<O> {
         }
     }
 
-    pub fn account_id(&mut self, account_id: impl Into<String>) -> &mut Self {
+    pub fn account_id(&mut self, account_id: impl Into<AccountId>) -> &mut Self {
         self.account_id = account_id.into();
         self
     }
@@ -153,3 +153,187 @@ impl AddedItem {
         self.index
     }
 }
+
+/// An operation to apply to a locally held list of query results.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryDiffOp {
+    Remove(usize),
+    Insert(usize, String),
+}
+
+/// The changes in a `queryChanges` response expressed as index based
+/// operations on the list of ids known to the client. Operations are meant to
+/// be applied in order.
+#[derive(Debug, Clone)]
+pub struct QueryDiff {
+    old_query_state: String,
+    new_query_state: String,
+    total: Option<usize>,
+    ops: Vec<QueryDiffOp>,
+    is_full_refresh: bool,
+}
+
+impl QueryDiff {
+    /// Builds the operations that turn `ids` into the new query results.
+    ///
+    /// When the changes were requested with `up_to_id`, only the ids up to and
+    /// including it are patched: additions past that id are offscreen and
+    /// skipped.
+    pub fn new(
+        ids: &[impl AsRef<str>],
+        response: &QueryChangesResponse,
+        up_to_id: Option<&str>,
+    ) -> Self {
+        let mut ids = ids.iter().map(|id| id.as_ref()).collect::<Vec<_>>();
+        if let Some(pos) = up_to_id.and_then(|up_to_id| ids.iter().position(|id| *id == up_to_id)) {
+            ids.truncate(pos + 1);
+        }
+
+        let mut ops = Vec::with_capacity(response.removed.len() + response.added.len());
+        let mut removed = ids
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| response.removed.iter().any(|removed| removed == *id))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        removed.reverse();
+        let mut len = ids.len() - removed.len();
+        ops.extend(removed.into_iter().map(QueryDiffOp::Remove));
+
+        let mut added = response.added.iter().collect::<Vec<_>>();
+        added.sort_unstable_by_key(|item| item.index);
+        for item in added {
+            if item.index < len || (item.index == len && up_to_id.is_none()) {
+                ops.push(QueryDiffOp::Insert(item.index, item.id.clone()));
+                len += 1;
+            }
+        }
+
+        QueryDiff {
+            old_query_state: response.old_query_state.clone(),
+            new_query_state: response.new_query_state.clone(),
+            total: response.total,
+            ops,
+            is_full_refresh: false,
+        }
+    }
+
+    /// Builds the operations that replace `ids` with the results of a new
+    /// query, used when the server cannot calculate the changes since
+    /// `old_query_state`.
+    pub fn replace(
+        ids: &[impl AsRef<str>],
+        old_query_state: impl Into<String>,
+        response: &QueryResponse,
+    ) -> Self {
+        let mut ops = Vec::with_capacity(ids.len() + response.ids().len());
+        ops.extend((0..ids.len()).rev().map(QueryDiffOp::Remove));
+        ops.extend(
+            response
+                .ids()
+                .iter()
+                .enumerate()
+                .map(|(index, id)| QueryDiffOp::Insert(index, id.clone())),
+        );
+
+        QueryDiff {
+            old_query_state: old_query_state.into(),
+            new_query_state: response.query_state().to_string(),
+            total: response.total(),
+            ops,
+            is_full_refresh: true,
+        }
+    }
+
+    /// Returns `true` if the results were fetched again with a new query
+    /// because the server could not calculate the changes.
+    pub fn is_full_refresh(&self) -> bool {
+        self.is_full_refresh
+    }
+
+    pub fn old_query_state(&self) -> &str {
+        &self.old_query_state
+    }
+
+    pub fn new_query_state(&self) -> &str {
+        &self.new_query_state
+    }
+
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    pub fn ops(&self) -> &[QueryDiffOp] {
+        &self.ops
+    }
+
+    pub fn take_ops(&mut self) -> Vec<QueryDiffOp> {
+        std::mem::take(&mut self.ops)
+    }
+
+    /// Applies the operations to `ids`.
+    pub fn apply(&self, ids: &mut Vec<String>) {
+        for op in &self.ops {
+            match op {
+                QueryDiffOp::Remove(index) => {
+                    ids.remove(*index);
+                }
+                QueryDiffOp::Insert(index, id) => ids.insert(*index, id.clone()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryChangesResponse, QueryDiff, QueryDiffOp};
+
+    #[test]
+    fn query_diff() {
+        let response: QueryChangesResponse = serde_json::from_value(serde_json::json!({
+            "accountId": "a",
+            "oldQueryState": "q1",
+            "newQueryState": "q2",
+            "total": 6,
+            "removed": ["b", "d", "x"],
+            "added": [
+                {"id": "f", "index": 4},
+                {"id": "d", "index": 0},
+                {"id": "g", "index": 2}
+            ]
+        }))
+        .unwrap();
+
+        let mut ids = ["a", "b", "c", "d", "e"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let diff = QueryDiff::new(&ids, &response, None);
+        assert_eq!(
+            diff.ops(),
+            [
+                QueryDiffOp::Remove(3),
+                QueryDiffOp::Remove(1),
+                QueryDiffOp::Insert(0, "d".to_string()),
+                QueryDiffOp::Insert(2, "g".to_string()),
+                QueryDiffOp::Insert(4, "f".to_string()),
+            ]
+        );
+        diff.apply(&mut ids);
+        assert_eq!(ids, ["d", "a", "g", "c", "f", "e"]);
+
+        // Only "a" to "c" are on screen, "f" would land past the boundary.
+        let mut ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let diff = QueryDiff::new(&ids, &response, Some("c"));
+        assert_eq!(
+            diff.ops(),
+            [
+                QueryDiffOp::Remove(1),
+                QueryDiffOp::Insert(0, "d".to_string()),
+                QueryDiffOp::Insert(2, "g".to_string()),
+            ]
+        );
+        diff.apply(&mut ids);
+        assert_eq!(ids, ["d", "a", "g", "c"]);
+    }
+}
@@ -28,16 +28,19 @@ use crate::{
 };
 use ahash::AHashMap;
 use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Display;
 
 use super::{
     changes::ChangesRequest,
     copy::CopyRequest,
+    error::{MethodError, MethodErrorType},
     get::GetRequest,
     query::QueryRequest,
     query_changes::QueryChangesRequest,
-    response::{Response, SingleMethodResponse, TaggedMethodResponse},
+    response::{LazyResponse, Response, SingleMethodResponse, TaggedMethodResponse},
+    session::Capabilities,
     set::SetRequest,
-    RequestParams,
+    AccountId, RequestParams,
 };
 
 #[derive(Serialize)]
@@ -45,7 +48,7 @@ pub struct Request<'x> {
     #[serde(skip)]
     client: &'x Client,
     #[serde(skip)]
-    account_id: String,
+    account_id: AccountId,
 
     pub using: Vec<URI>,
 
@@ -101,9 +104,14 @@ pub enum Arguments {
     PrincipalQuery(QueryRequest<Principal<Set>>),
     PrincipalQueryChanges(QueryChangesRequest<Principal<Set>>),
     PrincipalSet(SetRequest<Principal<Set>>),
+    Echo(serde_json::Value),
 }
 
 impl Arguments {
+    pub fn echo(arguments: serde_json::Value) -> Self {
+        Arguments::Echo(arguments)
+    }
+
     pub fn changes(params: RequestParams, since_state: String) -> Self {
         Arguments::Changes(ChangesRequest::new(params, since_state))
     }
@@ -116,7 +124,7 @@ impl Arguments {
         Arguments::PushSet(SetRequest::new(params))
     }
 
-    pub fn blob_copy(params: RequestParams, from_account_id: String) -> Self {
+    pub fn blob_copy(params: RequestParams, from_account_id: AccountId) -> Self {
         Arguments::BlobCopy(CopyBlobRequest::new(params, from_account_id))
     }
 
@@ -156,7 +164,7 @@ impl Arguments {
         Arguments::EmailSet(SetRequest::new(params))
     }
 
-    pub fn email_copy(params: RequestParams, from_account_id: String) -> Self {
+    pub fn email_copy(params: RequestParams, from_account_id: AccountId) -> Self {
         Arguments::EmailCopy(CopyRequest::new(params, from_account_id))
     }
 
@@ -239,6 +247,13 @@ impl Arguments {
         Arguments::PrincipalSet(SetRequest::new(params))
     }
 
+    pub fn echo_mut(&mut self) -> &mut serde_json::Value {
+        match self {
+            Arguments::Echo(ref mut r) => r,
+            _ => unreachable!(),
+        }
+    }
+
     pub fn changes_mut(&mut self) -> &mut ChangesRequest {
         match self {
             Arguments::Changes(ref mut r) => r,
@@ -479,23 +494,42 @@ impl<'x> Request<'x> {
             using: vec![URI::Core, URI::Mail],
             method_calls: vec![],
             created_ids: None,
-            account_id: client.default_account_id().to_string(),
+            account_id: client.default_account_id().clone(),
             client,
         }
     }
 
-    pub fn account_id(mut self, account_id: impl Into<String>) -> Self {
+    pub fn account_id(mut self, account_id: impl Into<AccountId>) -> Self {
         self.account_id = account_id.into();
         self
     }
 
     #[maybe_async::maybe_async]
     pub async fn send(self) -> crate::Result<Response<TaggedMethodResponse>> {
+        self.validate()?;
+        let mut response: Response<TaggedMethodResponse> = self.client.send(&self).await?;
+        if self.client.describe_references() {
+            for method_response in response.method_responses_mut() {
+                let call_id = method_response.call_id().to_string();
+                if let Some(err) = method_response.method_error_mut() {
+                    self.describe_references(&call_id, err);
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    /// Sends the request without deserializing the method results, which can
+    /// then be deserialized one at a time using [`Response::get`].
+    #[maybe_async::maybe_async]
+    pub async fn send_lazy(self) -> crate::Result<LazyResponse> {
+        self.validate()?;
         self.client.send(&self).await
     }
 
     #[cfg(feature = "websockets")]
     pub async fn send_ws(self) -> crate::Result<String> {
+        self.validate()?;
         self.client.send_ws(self).await
     }
 
@@ -504,17 +538,107 @@ impl<'x> Request<'x> {
     where
         T: DeserializeOwned,
     {
+        let call_id = self
+            .last_call_id()
+            .ok_or_else(|| Error::Internal("Request contains no method calls".to_string()))?
+            .to_string();
+        self.validate()?;
         let response: Response<SingleMethodResponse<T>> = self.client.send(&self).await?;
-        match response
-            .unwrap_method_responses()
-            .pop()
-            .ok_or_else(|| Error::Internal("Server returned no results".to_string()))?
+        match response.take_single(&call_id) {
+            Err(Error::Method(mut err)) if self.client.describe_references() => {
+                self.describe_references(&call_id, &mut err);
+                Err(Error::Method(err))
+            }
+            result => result,
+        }
+    }
+
+    /// Adds the result references used by the call `call_id` to the
+    /// description of an `invalidResultReference` error.
+    fn describe_references(&self, call_id: &str, err: &mut MethodError) {
+        if err.error() != &MethodErrorType::InvalidResultReference {
+            return;
+        }
+        let arguments = match self
+            .method_calls
+            .iter()
+            .find(|(_, _, id)| id == call_id)
+            .and_then(|(_, arguments, _)| serde_json::to_value(arguments).ok())
         {
-            SingleMethodResponse::Ok((_, response, _)) => Ok(response),
-            SingleMethodResponse::Error((_, err, _)) => Err(err.into()),
+            Some(serde_json::Value::Object(arguments)) => arguments,
+            _ => return,
+        };
+        let references = arguments
+            .iter()
+            .filter(|(name, _)| name.starts_with('#'))
+            .map(|(name, reference)| {
+                format!(
+                    "{} references {} {} at {}",
+                    name,
+                    reference["name"].as_str().unwrap_or_default(),
+                    reference["resultOf"].as_str().unwrap_or_default(),
+                    reference["path"].as_str().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>();
+        if !references.is_empty() {
+            err.append_description(&references.join(", "));
         }
     }
 
+    fn validate(&self) -> crate::Result<()> {
+        let session = self.client.session();
+        let mail_capabilities = session
+            .account(&self.account_id)
+            .and_then(|account| account.capability(URI::Mail.as_ref()))
+            .into_iter()
+            .chain(session.capability(URI::Mail))
+            .find_map(|capabilities| match capabilities {
+                Capabilities::Mail(capabilities) => Some(capabilities),
+                _ => None,
+            });
+
+        if self.client.validate_sort_options() {
+            let sort_options = mail_capabilities
+                .map(|capabilities| capabilities.email_query_sort_options())
+                .filter(|sort_options| !sort_options.is_empty());
+
+            if let Some(sort_options) = sort_options {
+                for (_, arguments, _) in &self.method_calls {
+                    if let Arguments::EmailQuery(query) = arguments {
+                        for comparator in query.comparators() {
+                            let property = comparator.arguments().property();
+                            if !sort_options.iter().any(|option| option == property) {
+                                return Err(Error::UnsupportedSort(property.to_string()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(max_keywords) =
+            mail_capabilities.and_then(|capabilities| capabilities.max_keywords_per_email())
+        {
+            for (_, arguments, _) in &self.method_calls {
+                let too_many_keywords = match arguments {
+                    Arguments::EmailSet(set) => set
+                        .objects()
+                        .any(|email| email.keywords_len() > max_keywords),
+                    Arguments::EmailImport(import) => import
+                        .emails()
+                        .any(|email| email.keywords_len() > max_keywords),
+                    _ => false,
+                };
+                if too_many_keywords {
+                    return Err(Error::TooManyKeywords(max_keywords));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn params(&self, method: Method) -> RequestParams {
         RequestParams {
             account_id: self.account_id.clone(),
@@ -523,6 +647,18 @@ impl<'x> Request<'x> {
         }
     }
 
+    /// Adds a `Core/echo` call, which takes no `accountId`. The server returns
+    /// `arguments` unchanged.
+    pub fn echo(&mut self, arguments: serde_json::Value) -> &mut serde_json::Value {
+        self.add_method_call(Method::Echo, Arguments::echo(arguments))
+            .echo_mut()
+    }
+
+    #[maybe_async::maybe_async]
+    pub async fn send_echo(self) -> crate::Result<serde_json::Value> {
+        self.send_single().await
+    }
+
     pub fn add_method_call(&mut self, method: Method, arguments: Arguments) -> &mut Arguments {
         let call_id = format!("s{}", self.method_calls.len());
         self.method_calls.push((method, arguments, call_id));
@@ -535,6 +671,36 @@ impl<'x> Request<'x> {
         }
     }
 
+    /// Adds the given capabilities, such as [`Capabilities::mail`], to the
+    /// `using` list of the request.
+    pub fn add_using(&mut self, uris: impl IntoIterator<Item = URI>) -> &mut Self {
+        for uri in uris {
+            self.add_capability(uri);
+        }
+        self
+    }
+
+    /// Returns the number of method calls queued in this request.
+    pub fn len(&self) -> usize {
+        self.method_calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.method_calls.is_empty()
+    }
+
+    /// Returns the names of the queued method calls, in the order they will be sent.
+    pub fn method_names(&self) -> Vec<&str> {
+        self.method_calls
+            .iter()
+            .map(|(method, _, _)| method.as_ref())
+            .collect()
+    }
+
+    pub fn last_call_id(&self) -> Option<&str> {
+        self.method_calls.last().map(|call| call.2.as_str())
+    }
+
     pub fn last_result_reference(&self, path: impl Into<String>) -> ResultReference {
         let last_method = self.method_calls.last().unwrap();
         ResultReference {
@@ -543,6 +709,18 @@ impl<'x> Request<'x> {
             path: path.into(),
         }
     }
+
+    /// Same as [`Request::last_result_reference`], failing with
+    /// [`Error::InvalidReference`] if `path` is not valid for the last method
+    /// call, see [`ResultReference::validate`].
+    pub fn try_last_result_reference(
+        &self,
+        path: impl Into<String>,
+    ) -> crate::Result<ResultReference> {
+        let reference = self.last_result_reference(path);
+        reference.validate()?;
+        Ok(reference)
+    }
 }
 
 impl ResultReference {
@@ -553,4 +731,386 @@ impl ResultReference {
             path: path.into(),
         }
     }
+
+    /// Same as [`ResultReference::new`], validating the path first.
+    pub fn try_new(method: Method, call_id: usize, path: impl Into<String>) -> crate::Result<Self> {
+        let reference = ResultReference::new(method, call_id, path);
+        reference.validate()?;
+        Ok(reference)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Checks that the path is a JSON pointer, with `*` allowed as an array
+    /// wildcard, and that its first segment is a property returned by the
+    /// referenced method when the method is a standard `get`, `changes`,
+    /// `query`, `queryChanges`, `set` or `copy` call.
+    pub fn validate(&self) -> crate::Result<()> {
+        let invalid = |reason: &str| {
+            Err(Error::InvalidReference(format!(
+                "{} path '{}': {}",
+                self.name.as_ref(),
+                self.path,
+                reason
+            )))
+        };
+        let mut segments = match self.path.strip_prefix('/') {
+            Some(segments) => segments.split('/'),
+            None => return invalid("must start with '/'"),
+        };
+        for segment in segments.clone() {
+            if segment.is_empty() {
+                return invalid("empty segment");
+            }
+            let mut chars = segment.chars();
+            while let Some(ch) = chars.next() {
+                if ch == '~' && !matches!(chars.next(), Some('0' | '1')) {
+                    return invalid("'~' must be followed by '0' or '1'");
+                }
+            }
+        }
+
+        let properties: &[&str] = match self.name.as_ref().rsplit_once('/') {
+            Some((_, "get")) => &["accountId", "state", "list", "notFound"],
+            Some((_, "changes")) => &[
+                "accountId",
+                "oldState",
+                "newState",
+                "hasMoreChanges",
+                "created",
+                "updated",
+                "destroyed",
+                "updatedProperties",
+            ],
+            Some((_, "query")) => &[
+                "accountId",
+                "queryState",
+                "canCalculateChanges",
+                "position",
+                "ids",
+                "total",
+                "limit",
+            ],
+            Some((_, "queryChanges")) => &[
+                "accountId",
+                "oldQueryState",
+                "newQueryState",
+                "total",
+                "removed",
+                "added",
+            ],
+            Some((_, "set")) => &[
+                "accountId",
+                "oldState",
+                "newState",
+                "created",
+                "updated",
+                "destroyed",
+                "notCreated",
+                "notUpdated",
+                "notDestroyed",
+            ],
+            Some((_, "copy")) => &[
+                "fromAccountId",
+                "accountId",
+                "oldState",
+                "newState",
+                "created",
+                "notCreated",
+            ],
+            _ => return Ok(()),
+        };
+        let first = segments.next().unwrap_or_default();
+        if properties.contains(&first) {
+            Ok(())
+        } else {
+            invalid(&format!("{} is not returned by this method", first))
+        }
+    }
+}
+
+const ARGUMENTS_PREVIEW_LEN: usize = 80;
+
+/// Lists the queued method calls, one per line, with their call id, method
+/// name and a truncated preview of their arguments.
+impl Display for Request<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (pos, (method, arguments, call_id)) in self.method_calls.iter().enumerate() {
+            if pos > 0 {
+                writeln!(f)?;
+            }
+            let arguments = serde_json::to_string(arguments).unwrap_or_default();
+            write!(f, "{} {} ", call_id, method.as_ref())?;
+            match arguments.char_indices().nth(ARGUMENTS_PREVIEW_LEN) {
+                Some((end, _)) => write!(f, "{}...", &arguments[..end])?,
+                None => write!(f, "{}", arguments)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::{
+        core::{query::Comparator, AccountId},
+        email,
+        test_server::{block_on, TestResponse, TestServer},
+        Error,
+    };
+
+    #[test]
+    fn describe_invalid_reference() {
+        use crate::core::error::MethodErrorType;
+
+        let server = TestServer::start(|_| {
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": [
+                    ["Email/query", {
+                        "accountId": "a",
+                        "queryState": "q1",
+                        "canCalculateChanges": true,
+                        "position": 0,
+                        "ids": []
+                    }, "s0"],
+                    ["error", {"type": "invalidResultReference"}, "s1"]
+                ]})
+                .to_string(),
+            )
+        });
+        let mut client = block_on(server.connect());
+        client.set_describe_references(true);
+
+        let mut request = client.build();
+        let reference = request.query_email().result_reference();
+        let bad_reference = request.last_result_reference("/list/*/threadId");
+        request
+            .get_email()
+            .ids_ref(reference)
+            .properties_ref(bad_reference);
+        let error = block_on(request.send())
+            .unwrap()
+            .take_method_response_by_id("s1")
+            .unwrap()
+            .unwrap_get_email()
+            .unwrap_err();
+        match error {
+            Error::Method(err) => {
+                assert_eq!(err.error(), &MethodErrorType::InvalidResultReference);
+                let description = err.description().unwrap();
+                assert!(description.contains("#ids references Email/query s0 at /ids"));
+                assert!(description
+                    .contains("#properties references Email/query s0 at /list/*/threadId"));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        client.set_describe_references(false);
+        let mut request = client.build();
+        let reference = request.query_email().result_reference();
+        request.get_email().ids_ref(reference);
+        match block_on(request.send())
+            .unwrap()
+            .take_method_response_by_id("s1")
+            .unwrap()
+            .unwrap_get_email()
+        {
+            Err(Error::Method(err)) => assert!(err.description().is_none()),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_result_reference() {
+        use crate::{core::request::ResultReference, Method};
+
+        for path in ["/ids", "/list/*/threadId", "/list/0/a~1b", "/created/c0/id"] {
+            let method = if path == "/ids" {
+                Method::QueryEmail
+            } else if path.starts_with("/created") {
+                Method::SetEmail
+            } else {
+                Method::GetEmail
+            };
+            ResultReference::try_new(method, 0, path).unwrap();
+        }
+        for (method, path) in [
+            (Method::QueryEmail, "ids"),
+            (Method::QueryEmail, "/ids/"),
+            (Method::GetEmail, "/list//threadId"),
+            (Method::GetEmail, "/list/*/a~2b"),
+            (Method::GetEmail, "/ids"),
+        ] {
+            assert!(
+                matches!(
+                    ResultReference::try_new(method, 0, path),
+                    Err(Error::InvalidReference(_))
+                ),
+                "{}",
+                path
+            );
+        }
+        ResultReference::try_new(Method::Echo, 0, "/anything").unwrap();
+
+        let server = TestServer::start(|_| TestResponse::new(404, ""));
+        let client = block_on(server.connect());
+        let mut request = client.build();
+        request.query_email();
+        assert!(request.try_last_result_reference("/id").is_err());
+        assert!(request.try_last_result_reference("/ids").is_ok());
+    }
+
+    #[test]
+    fn display_summary() {
+        let server = TestServer::start(|_| TestResponse::new(404, ""));
+        let client = block_on(server.connect());
+
+        let mut request = client.build();
+        request
+            .query_email()
+            .filter(email::query::Filter::subject("a".repeat(100)));
+        request.get_thread().ids(["t1"]);
+
+        let summary = request.to_string();
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("s0 Email/query {"));
+        assert!(lines[0].ends_with("..."));
+        assert_eq!(lines[1], r#"s1 Thread/get {"accountId":"a","ids":["t1"]}"#);
+    }
+
+    #[test]
+    fn method_names() {
+        let server = TestServer::start(|_| TestResponse::new(404, ""));
+        let client = block_on(server.connect());
+
+        let mut request = client.build();
+        assert!(request.is_empty());
+        request.query_email();
+        let reference = request.last_result_reference("/ids");
+        request.get_email().ids_ref(reference);
+        request.set_mailbox().create().name("Inbox");
+        request.get_thread();
+
+        assert_eq!(request.len(), 4);
+        assert_eq!(
+            request.method_names(),
+            ["Email/query", "Email/get", "Mailbox/set", "Thread/get"]
+        );
+    }
+
+    #[test]
+    fn account_id() {
+        let server = TestServer::start(|_| TestResponse::new(404, ""));
+        let mut client = block_on(server.connect());
+        assert_eq!(client.default_account_id(), &AccountId::from("a"));
+
+        client.set_default_account_id(AccountId::new("b"));
+        let mut request = client.build();
+        request.get_email();
+        request.query_email().account_id(String::from("c"));
+
+        let request = serde_json::to_value(&request).unwrap();
+        assert_eq!(request["methodCalls"][0][1]["accountId"], "b");
+        assert_eq!(request["methodCalls"][1][1]["accountId"], "c");
+    }
+
+    #[test]
+    fn unsupported_sort() {
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:mail"]["emailQuerySortOptions"] =
+                    serde_json::json!(["receivedAt"])
+            },
+            |_| TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#),
+        );
+
+        block_on(async {
+            let mut client = server.connect().await;
+            let result = client
+                .email_query(
+                    None::<email::query::Filter>,
+                    [Comparator::new(email::query::Comparator::Size)].into(),
+                )
+                .await;
+            assert!(matches!(result, Err(Error::UnsupportedSort(property)) if property == "size"));
+            assert!(server.requests().is_empty());
+
+            client.set_validate_sort_options(false);
+            let mut request = client.build();
+            request
+                .query_email()
+                .sort([Comparator::new(email::query::Comparator::Size)]);
+            request.send().await.unwrap();
+            assert_eq!(server.requests().len(), 1);
+        });
+    }
+
+    #[test]
+    fn too_many_keywords() {
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:mail"]["maxKeywordsPerEmail"] =
+                    2.into()
+            },
+            |_| TestResponse::json(200, r#"{"sessionState": "s1", "methodResponses": []}"#),
+        );
+
+        block_on(async {
+            let client = server.connect().await;
+            let mut request = client.build();
+            request
+                .set_email()
+                .create()
+                .keywords(["$seen", "$flagged", "$draft"]);
+            assert!(matches!(
+                request.send().await,
+                Err(Error::TooManyKeywords(2))
+            ));
+            assert!(server.requests().is_empty());
+
+            let mut request = client.build();
+            request.set_email().create().keywords(["$seen", "$flagged"]);
+            request
+                .set_email()
+                .update("m1")
+                .keyword("$draft", true)
+                .keyword("$answered", true)
+                .keyword("$forwarded", true);
+            request.send().await.unwrap();
+            assert_eq!(server.requests().len(), 1);
+
+            let mut request = client.build();
+            request
+                .import_email()
+                .email("b1")
+                .mailbox_ids(["inbox"])
+                .keywords(["$seen", "$flagged", "$draft"]);
+            assert!(matches!(
+                request.send().await,
+                Err(Error::TooManyKeywords(2))
+            ));
+            assert_eq!(server.requests().len(), 1);
+        });
+    }
+
+    #[test]
+    fn echo_has_no_account_id() {
+        let server = TestServer::start(|_| TestResponse::new(404, ""));
+        let client = block_on(server.connect());
+
+        let mut request = client.build();
+        request.echo(serde_json::json!({"hello": true}));
+        request.query_email();
+
+        let request = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            request["methodCalls"][0],
+            serde_json::json!(["Core/echo", {"hello": true}, "s0"])
+        );
+        assert_eq!(request["methodCalls"][1][1]["accountId"], "a");
+    }
 }
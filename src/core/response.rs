@@ -10,7 +10,11 @@
  */
 
 use ahash::AHashMap;
-use serde::{de::Visitor, Deserialize};
+use serde::{
+    de::{DeserializeOwned, IntoDeserializer, Visitor},
+    Deserialize,
+};
+use serde_json::value::RawValue;
 use std::fmt;
 
 use crate::{
@@ -31,13 +35,21 @@ use crate::{
 };
 
 use super::{
-    changes::ChangesResponse, copy::CopyResponse, error::MethodError, get::GetResponse,
-    query::QueryResponse, query_changes::QueryChangesResponse, set::SetResponse,
+    changes::ChangesResponse,
+    copy::CopyResponse,
+    error::{MethodError, MethodErrorType},
+    get::GetResponse,
+    query::QueryResponse,
+    query_changes::QueryChangesResponse,
+    set::SetResponse,
 };
 
 #[derive(Debug, Deserialize)]
 pub struct Response<T> {
     #[serde(rename = "methodResponses")]
+    #[serde(default = "Vec::new")]
+    #[serde(deserialize_with = "null_as_empty")]
+    #[serde(bound(deserialize = "T: Deserialize<'de>"))]
     method_responses: Vec<T>,
 
     #[serde(rename = "createdIds")]
@@ -49,6 +61,22 @@ pub struct Response<T> {
     request_id: Option<String>,
 }
 
+/// Some servers send a `null` instead of an empty `methodResponses` list.
+fn null_as_empty<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<Vec<T>>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+/// Used in strict mode to check that `methodResponses` is present.
+#[derive(Deserialize)]
+pub(crate) struct StrictResponse {
+    #[serde(rename = "methodResponses")]
+    _method_responses: Vec<serde::de::IgnoredAny>,
+}
+
 impl<T> Response<T> {
     pub fn new(
         method_responses: Vec<T>,
@@ -72,6 +100,10 @@ impl<T> Response<T> {
         self.method_responses
     }
 
+    pub(crate) fn method_responses_mut(&mut self) -> &mut [T] {
+        &mut self.method_responses
+    }
+
     pub fn method_response_by_pos(&mut self, index: usize) -> T {
         self.method_responses.remove(index)
     }
@@ -99,6 +131,77 @@ impl Response<TaggedMethodResponse> {
             .iter()
             .find(|response| response.call_id() == id)
     }
+
+    /// Returns `true` if the method call with id `call_id` failed with an
+    /// `unknownMethod` error, meaning that the server does not implement the
+    /// method although it accepted the capabilities of the request.
+    pub fn is_unknown_method(&self, call_id: &str) -> bool {
+        self.method_response_by_id(call_id)
+            .is_some_and(|response| response.is_unknown_method())
+    }
+
+    pub fn take_method_response_by_id(&mut self, id: &str) -> Option<TaggedMethodResponse> {
+        self.method_responses
+            .iter()
+            .position(|response| response.call_id() == id)
+            .map(|pos| self.method_responses.remove(pos))
+    }
+}
+
+impl<T> Response<SingleMethodResponse<T>> {
+    pub(crate) fn take_single(self, id: &str) -> crate::Result<T> {
+        let mut method_responses = self.method_responses;
+        let pos = method_responses
+            .iter()
+            .position(|response| response.call_id() == id)
+            .ok_or_else(|| {
+                crate::Error::Internal(format!("Server returned no results for call {}", id))
+            })?;
+        match method_responses.swap_remove(pos) {
+            SingleMethodResponse::Ok((_, response, _)) => Ok(response),
+            SingleMethodResponse::Error((_, err, _)) => Err(err.into()),
+        }
+    }
+}
+
+/// A method response whose arguments are kept as raw JSON until requested.
+#[derive(Debug, Deserialize)]
+pub struct RawMethodResponse(String, Box<RawValue>, String);
+
+/// A response that only deserializes the results of the method calls that
+/// are requested, see [`Response::get`].
+pub type LazyResponse = Response<RawMethodResponse>;
+
+impl Response<RawMethodResponse> {
+    /// Deserializes the result of the method call with id `call_id`.
+    pub fn get<T: DeserializeOwned>(&self, call_id: &str) -> crate::Result<T> {
+        let response = self
+            .method_responses
+            .iter()
+            .find(|response| response.call_id() == call_id)
+            .ok_or_else(|| {
+                crate::Error::Internal(format!("Server returned no results for call {}", call_id))
+            })?;
+        if response.name() == "error" {
+            Err(serde_json::from_str::<MethodError>(response.arguments())?.into())
+        } else {
+            Ok(serde_json::from_str(response.arguments())?)
+        }
+    }
+}
+
+impl RawMethodResponse {
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn arguments(&self) -> &str {
+        self.1.get()
+    }
+
+    pub fn call_id(&self) -> &str {
+        &self.2
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -108,6 +211,15 @@ pub enum SingleMethodResponse<T> {
     Ok((String, T, String)),
 }
 
+impl<T> SingleMethodResponse<T> {
+    pub fn call_id(&self) -> &str {
+        match self {
+            SingleMethodResponse::Error((_, _, id)) => id,
+            SingleMethodResponse::Ok((_, _, id)) => id,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub enum Error {
     #[serde(rename = "error")]
@@ -189,6 +301,10 @@ pub enum MethodResponse {
 
     Echo(serde_json::Value),
     Error(MethodError),
+    Unknown {
+        name: String,
+        arguments: serde_json::Value,
+    },
 }
 
 impl TaggedMethodResponse {
@@ -606,9 +722,30 @@ impl TaggedMethodResponse {
         }
     }
 
+    /// Returns the method name and raw arguments of a response to a method
+    /// not modeled by this crate.
+    pub fn unwrap_unknown(self) -> crate::Result<(String, serde_json::Value)> {
+        match self.response {
+            MethodResponse::Unknown { name, arguments } => Ok((name, arguments)),
+            MethodResponse::Error(err) => Err(err.into()),
+            _ => Err("Response type mismatch".into()),
+        }
+    }
+
     pub fn is_error(&self) -> bool {
         matches!(self.response, MethodResponse::Error(_))
     }
+
+    pub(crate) fn method_error_mut(&mut self) -> Option<&mut MethodError> {
+        match &mut self.response {
+            MethodResponse::Error(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    pub fn is_unknown_method(&self) -> bool {
+        matches!(&self.response, MethodResponse::Error(err) if err.error() == &MethodErrorType::UnknownMethod)
+    }
 }
 
 impl<'de> Deserialize<'de> for TaggedMethodResponse {
@@ -633,170 +770,183 @@ impl<'de> Visitor<'de> for TaggedMethodResponseVisitor {
     where
         A: serde::de::SeqAccess<'de>,
     {
-        let response = match seq
-            .next_element::<Method>()?
-            .ok_or_else(|| serde::de::Error::custom("Expected a method name"))?
-        {
-            Method::Echo => MethodResponse::Echo(
+        let name = seq
+            .next_element::<String>()?
+            .ok_or_else(|| serde::de::Error::custom("Expected a method name"))?;
+        let method = Method::deserialize(
+            IntoDeserializer::<serde::de::value::Error>::into_deserializer(name.as_str()),
+        )
+        .ok();
+
+        let response = match method {
+            Some(Method::Echo) => MethodResponse::Echo(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::CopyBlob => MethodResponse::CopyBlob(
+            Some(Method::CopyBlob) => MethodResponse::CopyBlob(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetPushSubscription => MethodResponse::GetPushSubscription(
+            Some(Method::GetPushSubscription) => MethodResponse::GetPushSubscription(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::SetPushSubscription => MethodResponse::SetPushSubscription(
+            Some(Method::SetPushSubscription) => MethodResponse::SetPushSubscription(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetMailbox => MethodResponse::GetMailbox(
+            Some(Method::GetMailbox) => MethodResponse::GetMailbox(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ChangesMailbox => MethodResponse::ChangesMailbox(
+            Some(Method::ChangesMailbox) => MethodResponse::ChangesMailbox(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QueryMailbox => MethodResponse::QueryMailbox(
+            Some(Method::QueryMailbox) => MethodResponse::QueryMailbox(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QueryChangesMailbox => MethodResponse::QueryChangesMailbox(
+            Some(Method::QueryChangesMailbox) => MethodResponse::QueryChangesMailbox(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::SetMailbox => MethodResponse::SetMailbox(
+            Some(Method::SetMailbox) => MethodResponse::SetMailbox(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetThread => MethodResponse::GetThread(
+            Some(Method::GetThread) => MethodResponse::GetThread(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ChangesThread => MethodResponse::ChangesThread(
+            Some(Method::ChangesThread) => MethodResponse::ChangesThread(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetEmail => MethodResponse::GetEmail(
+            Some(Method::GetEmail) => MethodResponse::GetEmail(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ChangesEmail => MethodResponse::ChangesEmail(
+            Some(Method::ChangesEmail) => MethodResponse::ChangesEmail(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QueryEmail => MethodResponse::QueryEmail(
+            Some(Method::QueryEmail) => MethodResponse::QueryEmail(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QueryChangesEmail => MethodResponse::QueryChangesEmail(
+            Some(Method::QueryChangesEmail) => MethodResponse::QueryChangesEmail(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::SetEmail => MethodResponse::SetEmail(
+            Some(Method::SetEmail) => MethodResponse::SetEmail(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::CopyEmail => MethodResponse::CopyEmail(
+            Some(Method::CopyEmail) => MethodResponse::CopyEmail(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ImportEmail => MethodResponse::ImportEmail(
+            Some(Method::ImportEmail) => MethodResponse::ImportEmail(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ParseEmail => MethodResponse::ParseEmail(
+            Some(Method::ParseEmail) => MethodResponse::ParseEmail(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetSearchSnippet => MethodResponse::GetSearchSnippet(
+            Some(Method::GetSearchSnippet) => MethodResponse::GetSearchSnippet(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetIdentity => MethodResponse::GetIdentity(
+            Some(Method::GetIdentity) => MethodResponse::GetIdentity(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ChangesIdentity => MethodResponse::ChangesIdentity(
+            Some(Method::ChangesIdentity) => MethodResponse::ChangesIdentity(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::SetIdentity => MethodResponse::SetIdentity(
+            Some(Method::SetIdentity) => MethodResponse::SetIdentity(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetEmailSubmission => MethodResponse::GetEmailSubmission(
+            Some(Method::GetEmailSubmission) => MethodResponse::GetEmailSubmission(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ChangesEmailSubmission => MethodResponse::ChangesEmailSubmission(
+            Some(Method::ChangesEmailSubmission) => MethodResponse::ChangesEmailSubmission(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QueryEmailSubmission => MethodResponse::QueryEmailSubmission(
+            Some(Method::QueryEmailSubmission) => MethodResponse::QueryEmailSubmission(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QueryChangesEmailSubmission => MethodResponse::QueryChangesEmailSubmission(
+            Some(Method::QueryChangesEmailSubmission) => {
+                MethodResponse::QueryChangesEmailSubmission(
+                    seq.next_element()?
+                        .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
+                )
+            }
+            Some(Method::SetEmailSubmission) => MethodResponse::SetEmailSubmission(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::SetEmailSubmission => MethodResponse::SetEmailSubmission(
+            Some(Method::GetVacationResponse) => MethodResponse::GetVacationResponse(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetVacationResponse => MethodResponse::GetVacationResponse(
+            Some(Method::SetVacationResponse) => MethodResponse::SetVacationResponse(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::SetVacationResponse => MethodResponse::SetVacationResponse(
+            Some(Method::GetSieveScript) => MethodResponse::GetSieveScript(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetSieveScript => MethodResponse::GetSieveScript(
+            Some(Method::SetSieveScript) => MethodResponse::SetSieveScript(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::SetSieveScript => MethodResponse::SetSieveScript(
+            Some(Method::QuerySieveScript) => MethodResponse::QuerySieveScript(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QuerySieveScript => MethodResponse::QuerySieveScript(
+            Some(Method::ValidateSieveScript) => MethodResponse::ValidateSieveScript(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ValidateSieveScript => MethodResponse::ValidateSieveScript(
+            Some(Method::GetPrincipal) => MethodResponse::GetPrincipal(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::GetPrincipal => MethodResponse::GetPrincipal(
+            Some(Method::ChangesPrincipal) => MethodResponse::ChangesPrincipal(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::ChangesPrincipal => MethodResponse::ChangesPrincipal(
+            Some(Method::QueryPrincipal) => MethodResponse::QueryPrincipal(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QueryPrincipal => MethodResponse::QueryPrincipal(
+            Some(Method::QueryChangesPrincipal) => MethodResponse::QueryChangesPrincipal(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::QueryChangesPrincipal => MethodResponse::QueryChangesPrincipal(
+            Some(Method::SetPrincipal) => MethodResponse::SetPrincipal(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::SetPrincipal => MethodResponse::SetPrincipal(
+            Some(Method::Error) => MethodResponse::Error(
                 seq.next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
             ),
-            Method::Error => MethodResponse::Error(
-                seq.next_element()?
+            None => MethodResponse::Unknown {
+                name,
+                arguments: seq
+                    .next_element()?
                     .ok_or_else(|| serde::de::Error::custom("Expected a method response"))?,
-            ),
+            },
         };
 
         let id = seq
@@ -806,3 +956,180 @@ impl<'de> Visitor<'de> for TaggedMethodResponseVisitor {
         Ok(TaggedMethodResponse { response, id })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        LazyResponse, MethodResponse, Response, SingleMethodResponse, TaggedMethodResponse,
+    };
+    use crate::{
+        core::{error::MethodErrorType, query::QueryResponse},
+        Error, Method,
+    };
+
+    const REVERSED: &[u8] = br#"{"sessionState": "123", "methodResponses": [
+        [ "Email/get", {
+            "accountId": "A1",
+            "state": "123456",
+            "list": [{ "id": "msg1023", "threadId": "trd194" }],
+            "notFound": []
+        }, "s1" ],
+        [ "Email/query", {
+            "accountId": "A1",
+            "queryState": "abcdefg",
+            "canCalculateChanges": true,
+            "position": 0,
+            "ids": [ "msg1023" ]
+        }, "s0" ]]}"#;
+
+    #[test]
+    fn match_by_call_id() {
+        let mut response: Response<TaggedMethodResponse> =
+            serde_json::from_slice(REVERSED).unwrap();
+        assert!(response
+            .method_response_by_id("s0")
+            .unwrap()
+            .is_type(Method::QueryEmail));
+
+        let mut get = response
+            .take_method_response_by_id("s1")
+            .unwrap()
+            .unwrap_get_email()
+            .unwrap();
+        assert_eq!(get.take_list()[0].id(), Some("msg1023"));
+        assert!(response.take_method_response_by_id("s1").is_none());
+
+        let query = response
+            .take_method_response_by_id("s0")
+            .unwrap()
+            .unwrap_query_email()
+            .unwrap();
+        assert_eq!(query.ids(), ["msg1023"]);
+    }
+
+    #[test]
+    fn match_single_by_call_id() {
+        let response: Response<SingleMethodResponse<serde_json::Value>> =
+            serde_json::from_slice(REVERSED).unwrap();
+        assert_eq!(response.take_single("s0").unwrap()["queryState"], "abcdefg");
+
+        let response: Response<SingleMethodResponse<serde_json::Value>> =
+            serde_json::from_slice(REVERSED).unwrap();
+        assert!(response.take_single("s2").is_err());
+    }
+
+    #[test]
+    fn method_error_response() {
+        let response: Response<TaggedMethodResponse> = serde_json::from_slice(
+            br#"{"sessionState": "123", "methodResponses": [
+            [ "Email/query", {
+                "accountId": "A1",
+                "queryState": "abcdefg",
+                "canCalculateChanges": true,
+                "position": 0,
+                "ids": [ "msg1023" ]
+            }, "s0" ],
+            [ "error", {
+                "type": "invalidArguments",
+                "description": "Unknown property foo"
+            }, "s1" ],
+            [ "error", { "type": "vendorSpecificFailure" }, "s2" ]]}"#,
+        )
+        .unwrap();
+        let mut responses = response.unwrap_method_responses().into_iter();
+
+        assert!(responses.next().unwrap().is_type(Method::QueryEmail));
+
+        let error = responses.next().unwrap();
+        assert!(error.is_error());
+        assert_eq!(error.call_id(), "s1");
+        match error.unwrap_get_email() {
+            Err(Error::Method(err)) => {
+                assert_eq!(err.error(), &MethodErrorType::InvalidArguments);
+                assert_eq!(err.description(), Some("Unknown property foo"));
+                assert_eq!(err.to_string(), "Invalid arguments: Unknown property foo");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        match responses.next().unwrap().unwrap_method_response() {
+            MethodResponse::Error(err) => assert_eq!(err.error(), &MethodErrorType::Unknown),
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_method_error() {
+        let response: Response<TaggedMethodResponse> = serde_json::from_slice(
+            br#"{"sessionState": "123", "methodResponses": [
+            [ "Email/query", {
+                "accountId": "A1",
+                "queryState": "abcdefg",
+                "canCalculateChanges": true,
+                "position": 0,
+                "ids": [ "msg1023" ]
+            }, "s0" ],
+            [ "error", { "type": "unknownMethod" }, "s1" ],
+            [ "error", { "type": "invalidArguments" }, "s2" ]]}"#,
+        )
+        .unwrap();
+        assert!(!response.is_unknown_method("s0"));
+        assert!(response.is_unknown_method("s1"));
+        assert!(!response.is_unknown_method("s2"));
+        assert!(!response.is_unknown_method("s3"));
+    }
+
+    #[test]
+    fn unknown_method_response() {
+        let response: Response<TaggedMethodResponse> = serde_json::from_slice(
+            br#"{"sessionState": "123", "methodResponses": [
+            [ "Email/query", {
+                "accountId": "A1",
+                "queryState": "abcdefg",
+                "canCalculateChanges": true,
+                "position": 0,
+                "ids": [ "msg1023" ]
+            }, "s0" ],
+            [ "Vendor/frobnicate", { "accountId": "A1", "frobs": 3 }, "s1" ]]}"#,
+        )
+        .unwrap();
+        let mut responses = response.unwrap_method_responses();
+
+        let (name, arguments) = responses.pop().unwrap().unwrap_unknown().unwrap();
+        assert_eq!(name, "Vendor/frobnicate");
+        assert_eq!(arguments["frobs"], 3);
+
+        let query = responses.pop().unwrap();
+        assert!(query.is_type(Method::QueryEmail));
+        assert_eq!(query.call_id(), "s0");
+    }
+
+    #[test]
+    fn lazy_response() {
+        // The Email/get result is malformed and would fail to parse eagerly.
+        let body = br#"{"sessionState": "123", "methodResponses": [
+            ["Email/query", {
+                "accountId": "A1",
+                "queryState": "q1",
+                "canCalculateChanges": true,
+                "position": 0,
+                "ids": ["m1", "m2"]
+            }, "s0"],
+            ["Email/get", {"accountId": 7, "list": "nope"}, "s1"],
+            ["error", {"type": "forbidden"}, "s2"]
+        ]}"#;
+        assert!(serde_json::from_slice::<Response<TaggedMethodResponse>>(body).is_err());
+
+        let response: LazyResponse = serde_json::from_slice(body).unwrap();
+        let query: QueryResponse = response.get("s0").unwrap();
+        assert_eq!(query.ids(), ["m1", "m2"]);
+        assert!(matches!(
+            response.get::<QueryResponse>("s2"),
+            Err(Error::Method(_))
+        ));
+        assert!(matches!(
+            response.get::<QueryResponse>("s9"),
+            Err(Error::Internal(_))
+        ));
+    }
+}
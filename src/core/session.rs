@@ -131,6 +131,33 @@ pub struct SieveCapabilities {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmptyCapabilities {}
 
+impl Capabilities {
+    /// Capabilities required by the mail methods.
+    pub fn mail() -> Vec<URI> {
+        vec![URI::Core, URI::Mail]
+    }
+
+    /// Capabilities required for submitting emails.
+    pub fn submission() -> Vec<URI> {
+        vec![URI::Core, URI::Mail, URI::Submission]
+    }
+
+    /// Capabilities required by the contacts methods.
+    pub fn contacts() -> Vec<URI> {
+        vec![URI::Core, URI::Contacts]
+    }
+
+    /// Capabilities required by the calendar methods.
+    pub fn calendars() -> Vec<URI> {
+        vec![URI::Core, URI::Calendars]
+    }
+
+    /// Capabilities required by the Sieve script methods.
+    pub fn sieve() -> Vec<URI> {
+        vec![URI::Core, URI::Sieve]
+    }
+}
+
 impl Session {
     pub fn capabilities(&self) -> impl Iterator<Item = &String> {
         self.capabilities.keys()
@@ -201,6 +228,24 @@ impl Session {
         self.primary_accounts.iter()
     }
 
+    pub fn primary_account_for(&self, capability: impl AsRef<str>) -> Option<&str> {
+        self.primary_accounts
+            .get(capability.as_ref())
+            .map(|id| id.as_str())
+    }
+
+    pub fn primary_mail_account(&self) -> Option<&str> {
+        self.primary_account_for(URI::Mail)
+    }
+
+    pub fn primary_contacts_account(&self) -> Option<&str> {
+        self.primary_account_for(URI::Contacts)
+    }
+
+    pub fn primary_calendar_account(&self) -> Option<&str> {
+        self.primary_account_for(URI::Calendars)
+    }
+
     pub fn username(&self) -> &str {
         &self.username
     }
@@ -226,6 +271,52 @@ impl Session {
     }
 }
 
+impl Session {
+    /// Resolves any relative URLs in the session against the URL the session
+    /// was fetched from, after following any redirects.
+    pub(crate) fn resolve_urls(&mut self, session_url: &reqwest::Url) {
+        for url in [
+            &mut self.api_url,
+            &mut self.download_url,
+            &mut self.upload_url,
+            &mut self.event_source_url,
+        ] {
+            if let Some(resolved) = resolve_url(session_url, url) {
+                *url = resolved;
+            }
+        }
+    }
+}
+
+// Resolves `url` against `base`, returns `None` if `url` is already absolute.
+// `Url::join` percent-encodes the braces of the URL template variables, so
+// these are swapped for placeholders while joining.
+fn resolve_url(base: &reqwest::Url, url: &str) -> Option<String> {
+    if url.is_empty() || reqwest::Url::parse(url).is_ok() {
+        return None;
+    }
+
+    let mut variables = Vec::new();
+    let mut template = String::with_capacity(url.len());
+    let mut rest = url;
+    while let Some((start, end)) = rest
+        .find('{')
+        .and_then(|start| Some((start, start + rest[start..].find('}')? + 1)))
+    {
+        template.push_str(&rest[..start]);
+        template.push_str(&format!("jmap-variable-{}", variables.len()));
+        variables.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    template.push_str(rest);
+
+    let mut resolved = base.join(&template).ok()?.to_string();
+    for (pos, variable) in variables.iter().enumerate().rev() {
+        resolved = resolved.replacen(&format!("jmap-variable-{}", pos), variable, 1);
+    }
+    Some(resolved)
+}
+
 impl Account {
     pub fn name(&self) -> &str {
         &self.name
@@ -322,6 +413,19 @@ impl SieveCapabilities {
     }
 }
 
+/// Appends the value of a template variable to `url`, percent-encoding
+/// any character other than the unreserved ones as required by the
+/// simple string expansion of RFC 6570.
+pub(crate) fn push_url_value(url: &mut String, value: &str) {
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+}
+
 pub trait URLParser: Sized {
     fn parse(value: &str) -> Option<Self>;
 }
@@ -377,3 +481,111 @@ impl<T: URLParser> URLPart<T> {
         Ok(parts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::URI;
+
+    use super::{Capabilities, Session};
+
+    #[test]
+    fn capability_presets() {
+        assert_eq!(
+            serde_json::to_value(Capabilities::mail()).unwrap(),
+            serde_json::json!(["urn:ietf:params:jmap:core", "urn:ietf:params:jmap:mail"])
+        );
+        assert!(Capabilities::submission().contains(&URI::Submission));
+        assert_eq!(
+            Capabilities::contacts()[1].as_ref(),
+            "urn:ietf:params:jmap:contacts"
+        );
+    }
+
+    #[test]
+    fn primary_accounts() {
+        let session: Session = serde_json::from_str(
+            r#"{
+            "capabilities": {},
+            "accounts": {},
+            "primaryAccounts": {
+                "urn:ietf:params:jmap:mail": "m1",
+                "urn:ietf:params:jmap:calendars": "c1"
+            },
+            "username": "jdoe@example.org",
+            "apiUrl": "https://jmap.example.org/api/",
+            "downloadUrl": "https://jmap.example.org/download/{accountId}/{blobId}/{name}?accept={type}",
+            "uploadUrl": "https://jmap.example.org/upload/{accountId}/",
+            "eventSourceUrl": "https://jmap.example.org/eventsource/?types={types}&closeafter={closeafter}&ping={ping}",
+            "state": "75128aab4b1b"
+        }"#,
+        )
+        .unwrap();
+
+        assert_eq!(session.primary_mail_account(), Some("m1"));
+        assert_eq!(session.primary_calendar_account(), Some("c1"));
+        assert_eq!(session.primary_contacts_account(), None);
+        assert_eq!(
+            session.primary_account_for("urn:ietf:params:jmap:calendars"),
+            Some("c1")
+        );
+    }
+
+    #[test]
+    fn resolve_relative_urls() {
+        let mut session: Session = serde_json::from_str(
+            r#"{
+            "capabilities": {},
+            "accounts": {},
+            "primaryAccounts": {},
+            "username": "jdoe@example.org",
+            "apiUrl": "/jmap/",
+            "downloadUrl": "/jmap/download/{accountId}/{blobId}/{name}?accept={type}",
+            "uploadUrl": "upload/{accountId}/",
+            "eventSourceUrl": "https://push.example.org/eventsource/?types={types}",
+            "state": "75128aab4b1b"
+        }"#,
+        )
+        .unwrap();
+        session.resolve_urls(
+            &"https://jmap.example.org:8080/.well-known/jmap"
+                .parse()
+                .unwrap(),
+        );
+
+        assert_eq!(session.api_url(), "https://jmap.example.org:8080/jmap/");
+        assert_eq!(
+            session.download_url(),
+            "https://jmap.example.org:8080/jmap/download/{accountId}/{blobId}/{name}?accept={type}"
+        );
+        assert_eq!(
+            session.upload_url(),
+            "https://jmap.example.org:8080/.well-known/upload/{accountId}/"
+        );
+        assert_eq!(
+            session.event_source_url(),
+            "https://push.example.org/eventsource/?types={types}"
+        );
+
+        let resolve = |base: &str, url: &str| super::resolve_url(&base.parse().unwrap(), url);
+        assert_eq!(
+            resolve("https://example.org", "jmap"),
+            Some("https://example.org/jmap".to_string())
+        );
+        assert_eq!(
+            resolve("https://example.org/a/b", "//cdn.example.org/x"),
+            Some("https://cdn.example.org/x".to_string())
+        );
+        assert_eq!(
+            resolve(
+                "https://example.org/a/b",
+                "/jmap/?next=https://example.org/"
+            ),
+            Some("https://example.org/jmap/?next=https://example.org/".to_string())
+        );
+        assert_eq!(
+            resolve("https://example.org/a/b", "../{accountId}/{blobId}"),
+            Some("https://example.org/{accountId}/{blobId}".to_string())
+        );
+        assert_eq!(resolve("https://example.org", "https://example.com/"), None);
+    }
+}
@@ -15,7 +15,10 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display, Formatter};
 
-use super::{request::ResultReference, Object, RequestParams};
+use super::{request::ResultReference, AccountId, Object, RequestParams};
+
+/// Fixed id of singleton objects such as `VacationResponse`.
+pub const SINGLETON_ID: &str = "singleton";
 
 pub trait SetObject: Object {
     type SetArguments: Default;
@@ -28,7 +31,7 @@ pub trait SetObject: Object {
 pub struct SetRequest<O: SetObject> {
     #[serde(rename = "accountId")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    account_id: Option<String>,
+    account_id: Option<AccountId>,
 
     #[serde(rename = "ifInState")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -91,6 +94,9 @@ where
     pub type_: SetErrorType,
     description: Option<String>,
     properties: Option<Vec<U>>,
+    #[serde(rename = "existingId")]
+    #[serde(default)]
+    existing_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
@@ -164,7 +170,7 @@ impl<O: SetObject> SetRequest<O> {
         }
     }
 
-    pub fn account_id(&mut self, account_id: impl Into<String>) -> &mut Self {
+    pub fn account_id(&mut self, account_id: impl Into<AccountId>) -> &mut Self {
         if O::requires_account_id() {
             self.account_id = Some(account_id.into());
         }
@@ -241,6 +247,48 @@ impl<O: SetObject> SetRequest<O> {
     pub fn arguments(&mut self) -> &mut O::SetArguments {
         &mut self.arguments
     }
+
+    /// Restricts this request to the singleton object of its type.
+    pub fn singleton(&mut self) -> SingletonSet<'_, O> {
+        SingletonSet { request: self }
+    }
+
+    pub(crate) fn objects(&self) -> impl Iterator<Item = &O> {
+        self.create
+            .iter()
+            .chain(self.update.iter())
+            .flat_map(|objects| objects.values())
+    }
+}
+
+/// Set request on an object type that exists exactly once per account under
+/// the id [`SINGLETON_ID`]. The singleton can only be updated; attempting to
+/// create or destroy it fails with a `singleton` error without contacting
+/// the server.
+pub struct SingletonSet<'x, O: SetObject> {
+    request: &'x mut SetRequest<O>,
+}
+
+impl<O: SetObject> SingletonSet<'_, O> {
+    pub fn update(&mut self) -> &mut O {
+        self.request.update(SINGLETON_ID)
+    }
+
+    pub fn update_item(&mut self, item: O) {
+        self.request.update_item(SINGLETON_ID, item)
+    }
+
+    pub fn create(&mut self) -> crate::Result<&mut O> {
+        Err(Error::Set(SetError::singleton(
+            "Singleton objects cannot be created.",
+        )))
+    }
+
+    pub fn destroy(&mut self) -> crate::Result<()> {
+        Err(Error::Set(SetError::singleton(
+            "Singleton objects cannot be destroyed.",
+        )))
+    }
 }
 
 impl<O: SetObject> SetResponse<O> {
@@ -260,6 +308,9 @@ impl<O: SetObject> SetResponse<O> {
         self.new_state.take().unwrap_or_default()
     }
 
+    /// Takes the object created for the creation id `id`. It only holds the
+    /// properties set by the server, such as `id` and, for emails, the
+    /// `blobId` of the raw message.
     pub fn created(&mut self, id: &str) -> crate::Result<O> {
         if let Some(result) = self.created.as_mut().and_then(|r| r.remove(id)) {
             Ok(result)
@@ -372,6 +423,20 @@ impl<U: Display> SetError<U> {
         self.properties.as_deref()
     }
 
+    /// Id of the existing object, returned with `alreadyExists` errors.
+    pub fn existing_id(&self) -> Option<&str> {
+        self.existing_id.as_deref()
+    }
+
+    fn singleton(description: &str) -> Self {
+        SetError {
+            type_: SetErrorType::Singleton,
+            description: Some(description.to_string()),
+            properties: None,
+            existing_id: None,
+        }
+    }
+
     pub fn to_string_error(&self) -> SetError<String> {
         SetError {
             type_: self.type_.clone(),
@@ -380,6 +445,7 @@ impl<U: Display> SetError<U> {
                 .properties
                 .as_ref()
                 .map(|s| s.iter().map(|s| s.to_string()).collect()),
+            existing_id: self.existing_id.clone(),
         }
     }
 }
@@ -69,6 +69,11 @@ impl Email<Get> {
         self.size.unwrap_or(0)
     }
 
+    /// Returns the size of the message formatted for display, such as "1.2 MB".
+    pub fn size_human(&self) -> String {
+        format_size(self.size())
+    }
+
     pub fn received_at(&self) -> Option<i64> {
         self.received_at.as_ref().map(|r| r.timestamp())
     }
@@ -293,6 +298,23 @@ impl EmailHeader<Get> {
     }
 }
 
+fn format_size(size: usize) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
 impl GetObject for Email<Set> {
     type GetArguments = GetArguments;
 }
@@ -300,3 +322,43 @@ impl GetObject for Email<Set> {
 impl GetObject for Email<Get> {
     type GetArguments = GetArguments;
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::email::Email;
+
+    fn email_with_size(size: usize) -> Email {
+        serde_json::from_str(&format!("{{\"size\": {}}}", size)).unwrap()
+    }
+
+    #[test]
+    fn size_human() {
+        for (size, expected) in [
+            (0, "0 B"),
+            (1023, "1023 B"),
+            (1024, "1.0 KB"),
+            (1536, "1.5 KB"),
+            (1024 * 1024 - 1, "1024.0 KB"),
+            (1024 * 1024, "1.0 MB"),
+            (1258291, "1.2 MB"),
+            (1024 * 1024 * 1024, "1.0 GB"),
+        ] {
+            assert_eq!(
+                email_with_size(size).size_human(),
+                expected,
+                "size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn preview() {
+        let email: Email = serde_json::from_str(r#"{"preview": "Hello world"}"#).unwrap();
+        assert_eq!(email.preview(), Some("Hello world"));
+        assert_eq!(
+            serde_json::to_string(&crate::email::Property::Preview).unwrap(),
+            "\"preview\""
+        );
+    }
+}
@@ -9,26 +9,32 @@
  * except according to those terms.
  */
 
+use ahash::AHashSet;
+
 use crate::{
     client::Client,
     core::{
-        changes::{ChangesRequest, ChangesResponse},
+        batch::ChunkResults,
+        changes::{ChangesRequest, ChangesResponse, SyncChanges},
         copy::CopyRequest,
+        error::MethodErrorType,
         get::GetRequest,
-        query::{Comparator, Filter, QueryRequest, QueryResponse},
-        query_changes::{QueryChangesRequest, QueryChangesResponse},
+        query::{Comparator, Filter, QueryPage, QueryRequest, QueryResponse},
+        query_changes::{QueryChangesRequest, QueryChangesResponse, QueryDiff},
         request::{Arguments, Request},
         response::{EmailCopyResponse, EmailGetResponse, EmailSetResponse},
         set::SetRequest,
+        AccountId,
     },
-    Get, Method, Set,
+    mailbox::Mailbox,
+    Error, Get, Method, Set, TypeState,
 };
 
 use super::{
     import::{EmailImportRequest, EmailImportResponse},
     parse::{EmailParseRequest, EmailParseResponse},
     search_snippet::{SearchSnippetGetRequest, SearchSnippetGetResponse},
-    BodyProperty, Email, Property,
+    BodyProperty, Email, EmailAddress, EmailBodyPart, EmailPatch, Property,
 };
 
 impl Client {
@@ -59,7 +65,7 @@ impl Client {
     #[maybe_async::maybe_async]
     pub async fn email_import_account<T, U, V, W>(
         &self,
-        account_id: &str,
+        account_id: impl Into<AccountId>,
         raw_message: Vec<u8>,
         mailbox_ids: T,
         keywords: Option<V>,
@@ -71,8 +77,9 @@ impl Client {
         V: IntoIterator<Item = W>,
         W: Into<String>,
     {
+        let account_id = account_id.into();
         let blob_id = self
-            .upload(account_id.into(), raw_message, None)
+            .upload(Some(&account_id), raw_message, None)
             .await?
             .take_blob_id();
         let mut request = self.build();
@@ -97,6 +104,109 @@ impl Client {
             .created(&id)
     }
 
+    /// Returns the `to` and `cc` recipients of a reply to all on `email`. The
+    /// original `replyTo` addresses, or `from` if there are none, are placed
+    /// first in `to` followed by the original `to`, while `cc` is kept.
+    /// Addresses in `identity_emails` and duplicates are removed.
+    pub fn reply_all_recipients<T, U>(
+        &self,
+        email: &Email,
+        identity_emails: T,
+    ) -> (Vec<EmailAddress>, Vec<EmailAddress>)
+    where
+        T: IntoIterator<Item = U>,
+        U: AsRef<str>,
+    {
+        let mut seen = identity_emails
+            .into_iter()
+            .map(|email| email.as_ref().to_lowercase())
+            .collect::<AHashSet<_>>();
+        let mut keep = |address: &&EmailAddress| seen.insert(address.email().to_lowercase());
+
+        let sender = email
+            .reply_to()
+            .filter(|reply_to| !reply_to.is_empty())
+            .or_else(|| email.from())
+            .unwrap_or_default();
+        let to = sender
+            .iter()
+            .chain(email.to().unwrap_or_default())
+            .filter(&mut keep)
+            .cloned()
+            .collect();
+        let cc = email
+            .cc()
+            .unwrap_or_default()
+            .iter()
+            .filter(&mut keep)
+            .cloned()
+            .collect();
+        (to, cc)
+    }
+
+    /// Creates `email`, uploading any body value larger than
+    /// [`Client::body_upload_threshold`] as a blob and referencing it from its
+    /// body part, which keeps the request within `maxSizeRequest`.
+    ///
+    /// Fails with [`Error::AttachmentsTooLarge`] before uploading anything when
+    /// the size of the attachments exceeds the server's
+    /// `maxSizeAttachmentsPerEmail`. Attachments referenced by blob id are only
+    /// counted when their size is known.
+    #[maybe_async::maybe_async]
+    pub async fn email_create(&self, mut email: Email<Set>) -> crate::Result<Email> {
+        if let Some(max) = self
+            .session()
+            .mail_capabilities()
+            .map(|capabilities| capabilities.max_size_attachments_per_email())
+        {
+            let total = attachments_size(&email);
+            if total > max {
+                return Err(Error::AttachmentsTooLarge { total, max });
+            }
+        }
+
+        if let Some(mut body_values) = email.body_values.take() {
+            let part_ids = body_values
+                .iter()
+                .filter(|(_, value)| value.value.len() > self.body_upload_threshold())
+                .map(|(part_id, _)| part_id.clone())
+                .collect::<Vec<_>>();
+            for part_id in part_ids {
+                let value = body_values.remove(&part_id).unwrap().value;
+                let mut content_type = None;
+                visit_body_parts(&mut email, &mut |part| {
+                    if part.part_id.as_deref() == Some(&part_id) && content_type.is_none() {
+                        content_type = part.type_.clone();
+                    }
+                });
+                let content_type = content_type.unwrap_or_else(|| "text/plain".to_string());
+                let blob_id = self
+                    .upload(None, value.into_bytes(), Some(&content_type))
+                    .await?
+                    .take_blob_id();
+                visit_body_parts(&mut email, &mut |part| {
+                    if part.part_id.as_deref() == Some(&part_id) {
+                        part.part_id = None;
+                        part.blob_id = Some(blob_id.clone());
+                        if part.charset.is_none() {
+                            part.charset = Some("utf-8".to_string());
+                        }
+                    }
+                });
+            }
+            if !body_values.is_empty() {
+                email.body_values = Some(body_values);
+            }
+        }
+
+        let mut request = self.build();
+        let id = request.set_email().create_item(email);
+        request
+            .send_single::<EmailSetResponse>()
+            .await?
+            .created(&id)
+    }
+
     #[maybe_async::maybe_async]
     pub async fn email_set_mailbox(
         &self,
@@ -109,6 +219,25 @@ impl Client {
         request.send_single::<EmailSetResponse>().await?.updated(id)
     }
 
+    /// Same as [`Client::email_set_mailbox`], failing early with
+    /// [`Error::Forbidden`] when the rights fetched for `mailbox` do not allow
+    /// adding or removing emails.
+    #[maybe_async::maybe_async]
+    pub async fn email_set_mailbox_checked(
+        &self,
+        id: &str,
+        mailbox: &Mailbox,
+        set: bool,
+    ) -> crate::Result<Option<Email>> {
+        if set {
+            mailbox.require_right("mayAddItems", |rights| rights.may_add_items())?;
+        } else {
+            mailbox.require_right("mayRemoveItems", |rights| rights.may_remove_items())?;
+        }
+        self.email_set_mailbox(id, mailbox.id().unwrap_or_default(), set)
+            .await
+    }
+
     #[maybe_async::maybe_async]
     pub async fn email_set_mailboxes<T, U>(
         &self,
@@ -136,6 +265,28 @@ impl Client {
         request.send_single::<EmailSetResponse>().await?.updated(id)
     }
 
+    /// Same as [`Client::email_set_keyword`], failing early with
+    /// [`Error::Forbidden`] when the rights fetched for `mailbox`, which
+    /// contains the email, do not allow changing the keyword.
+    #[maybe_async::maybe_async]
+    pub async fn email_set_keyword_checked(
+        &self,
+        id: &str,
+        mailbox: &Mailbox,
+        keyword: &str,
+        set: bool,
+    ) -> crate::Result<Option<Email>> {
+        if keyword == "$seen" {
+            mailbox.require_right("maySetSeen", |rights| rights.may_set_seen())?;
+        } else {
+            mailbox.require_right("maySetKeywords", |rights| rights.may_set_keywords())?;
+        }
+        self.email_set_keyword(id, keyword, set).await
+    }
+
+    /// Adds the given keywords to a message, leaving any other keywords it
+    /// already has in place. Each keyword is sent as a separate
+    /// `keywords/<keyword>` patch.
     #[maybe_async::maybe_async]
     pub async fn email_set_keywords<T, U>(
         &self,
@@ -147,10 +298,153 @@ impl Client {
         U: Into<String>,
     {
         let mut request = self.build();
-        request.set_email().update(id).keywords(keywords);
+        let email = request.set_email().update(id);
+        for keyword in keywords {
+            email.keyword(&keyword.into(), true);
+        }
         request.send_single::<EmailSetResponse>().await?.updated(id)
     }
 
+    /// Replaces the keywords of one or more messages with the given list,
+    /// removing any keyword not included in it.
+    #[maybe_async::maybe_async]
+    pub async fn email_replace_keywords<T, U, V, W>(&self, ids: T, keywords: V) -> crate::Result<()>
+    where
+        T: IntoIterator<Item = U>,
+        U: Into<String>,
+        V: IntoIterator<Item = W>,
+        W: Into<String>,
+    {
+        let keywords = keywords
+            .into_iter()
+            .map(|keyword| keyword.into())
+            .collect::<Vec<String>>();
+        let ids = ids.into_iter().map(|id| id.into()).collect::<Vec<String>>();
+        let mut request = self.build();
+        let set_request = request.set_email();
+        for id in &ids {
+            set_request.update(id).keywords(keywords.iter().cloned());
+        }
+        let mut response = request.send_single::<EmailSetResponse>().await?;
+        for id in &ids {
+            response.updated(id)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the same patch to all the given emails, splitting the updates
+    /// into as many `Email/set` requests as required by the server's
+    /// `maxObjectsInSet` limit.
+    #[maybe_async::maybe_async]
+    pub async fn email_apply_patch<T, U>(&self, ids: T, patch: &EmailPatch) -> crate::Result<()>
+    where
+        T: IntoIterator<Item = U>,
+        U: Into<String>,
+    {
+        self.email_apply_patch_chunked(ids, patch, true)
+            .await?
+            .into_result()
+    }
+
+    /// Same as [`Client::email_apply_patch`], reporting the outcome of each
+    /// request sent. Unless `fail_fast` is set, a failed request does not stop
+    /// the remaining ones from being sent; otherwise its error is returned.
+    #[maybe_async::maybe_async]
+    pub async fn email_apply_patch_chunked<T, U>(
+        &self,
+        ids: T,
+        patch: &EmailPatch,
+        fail_fast: bool,
+    ) -> crate::Result<ChunkResults>
+    where
+        T: IntoIterator<Item = U>,
+        U: Into<String>,
+    {
+        self.email_update_chunked(ids, fail_fast, |email| {
+            email.apply_patch(patch);
+        })
+        .await
+    }
+
+    /// Moves the given emails to `mailbox_id`, removing them from all the
+    /// other mailboxes they belong to.
+    #[maybe_async::maybe_async]
+    pub async fn email_move_to_mailbox<T, U>(&self, ids: T, mailbox_id: &str) -> crate::Result<()>
+    where
+        T: IntoIterator<Item = U>,
+        U: Into<String>,
+    {
+        self.email_update_chunked(ids, true, |email| {
+            email.mailbox_ids([mailbox_id]);
+        })
+        .await?
+        .into_result()
+    }
+
+    /// Moves the given emails from `from_mailbox_id` to `to_mailbox_id`,
+    /// keeping any other mailboxes they belong to, as emails may be in
+    /// several mailboxes at once on servers that use them as labels.
+    #[maybe_async::maybe_async]
+    pub async fn email_move_between<T, U>(
+        &self,
+        ids: T,
+        from_mailbox_id: &str,
+        to_mailbox_id: &str,
+    ) -> crate::Result<()>
+    where
+        T: IntoIterator<Item = U>,
+        U: Into<String>,
+    {
+        let mut patch = EmailPatch::new();
+        patch
+            .remove_mailbox(from_mailbox_id)
+            .add_mailbox(to_mailbox_id);
+        self.email_apply_patch(ids, &patch).await
+    }
+
+    #[maybe_async::maybe_async]
+    async fn email_update_chunked<T, U>(
+        &self,
+        ids: T,
+        fail_fast: bool,
+        update: impl Fn(&mut Email<Set>),
+    ) -> crate::Result<ChunkResults>
+    where
+        T: IntoIterator<Item = U>,
+        U: Into<String>,
+    {
+        let ids = ids.into_iter().map(|id| id.into()).collect::<Vec<String>>();
+        let max_objects_in_set = self
+            .session()
+            .core_capabilities()
+            .map(|capabilities| capabilities.max_objects_in_set())
+            .unwrap_or(ids.len())
+            .max(1);
+
+        let mut results = ChunkResults::default();
+        for chunk in ids.chunks(max_objects_in_set) {
+            let mut request = self.build();
+            let set_request = request.set_email();
+            for id in chunk {
+                update(set_request.update(id));
+            }
+            let result = match request.send_single::<EmailSetResponse>().await {
+                Ok(mut response) => chunk
+                    .iter()
+                    .try_for_each(|id| response.updated(id).map(|_| ())),
+                Err(err) => Err(err),
+            };
+            if fail_fast {
+                result?;
+                results.push(chunk, Ok(()));
+            } else {
+                results.push(chunk, result);
+            }
+        }
+
+        Ok(results)
+    }
+
     #[maybe_async::maybe_async]
     pub async fn email_destroy(&self, id: &str) -> crate::Result<()> {
         let mut request = self.build();
@@ -161,6 +455,15 @@ impl Client {
             .destroyed(id)
     }
 
+    /// Same as [`Client::email_destroy`], failing early with
+    /// [`Error::Forbidden`] when the rights fetched for `mailbox`, which
+    /// contains the email, do not allow removing emails.
+    #[maybe_async::maybe_async]
+    pub async fn email_destroy_checked(&self, id: &str, mailbox: &Mailbox) -> crate::Result<()> {
+        mailbox.require_right("mayRemoveItems", |rights| rights.may_remove_items())?;
+        self.email_destroy(id).await
+    }
+
     #[maybe_async::maybe_async]
     pub async fn email_get(
         &self,
@@ -178,6 +481,17 @@ impl Client {
             .map(|mut r| r.take_list().pop())
     }
 
+    /// Downloads the raw RFC 5322 source of an email, fetching its `blobId` first.
+    #[maybe_async::maybe_async]
+    pub async fn download_email_source(&self, email_id: &str) -> crate::Result<Vec<u8>> {
+        let blob_id = self
+            .email_get(email_id, [Property::BlobId].into())
+            .await?
+            .and_then(|email| email.blob_id().map(String::from))
+            .ok_or_else(|| Error::Internal(format!("Id {} not found.", email_id)))?;
+        self.download(&blob_id).await
+    }
+
     #[maybe_async::maybe_async]
     pub async fn email_changes(
         &self,
@@ -192,6 +506,96 @@ impl Client {
         request.send_single().await
     }
 
+    /// Fetches the changes since `since_state`, following `hasMoreChanges`
+    /// for at most `max_iterations` calls of up to `max_changes` changes each.
+    /// When the limit is reached before all the changes are fetched, the
+    /// result reports [`SyncChanges::has_more`] and can be resumed from its new
+    /// state. The new state is also recorded in [`Client::export_sync_state`].
+    ///
+    /// When the server cannot calculate the changes, the current Email state
+    /// is fetched instead and the result reports
+    /// [`SyncChanges::is_full_refresh`].
+    #[maybe_async::maybe_async]
+    pub async fn email_sync_changes(
+        &self,
+        since_state: impl Into<String>,
+        max_changes: Option<usize>,
+        max_iterations: usize,
+    ) -> crate::Result<SyncChanges> {
+        let mut sync = SyncChanges::new(since_state.into());
+        while sync.iterations() < max_iterations.max(1) {
+            match self
+                .email_changes(sync.new_state().to_string(), max_changes)
+                .await
+            {
+                Ok(changes) => sync.merge(changes),
+                Err(err) if err.is_cannot_calculate_changes() => {
+                    let new_state = self
+                        .current_states([TypeState::Email])
+                        .await?
+                        .remove(&TypeState::Email)
+                        .unwrap_or_default();
+                    sync.full_refresh(new_state);
+                }
+                Err(err) => return Err(err),
+            }
+            self.update_sync_state(|state| {
+                state.set_state(TypeState::Email, sync.new_state());
+            });
+            if !sync.has_more() {
+                break;
+            }
+        }
+        Ok(sync)
+    }
+
+    /// Fetches the changes since `since_state` together with the created and
+    /// updated emails, in a single request. Emails destroyed between the
+    /// `changes` and `get` calls are reported in the destroyed list of the
+    /// returned changes instead.
+    #[maybe_async::maybe_async]
+    pub async fn email_changes_fetch(
+        &self,
+        since_state: impl Into<String>,
+        max_changes: Option<usize>,
+        properties: Option<impl IntoIterator<Item = Property>>,
+    ) -> crate::Result<(ChangesResponse<Email<Get>>, Vec<Email>)> {
+        let properties = properties.map(|p| p.into_iter().collect::<Vec<_>>());
+        let mut request = self.build();
+        let changes_request = request.changes_email(since_state);
+        if let Some(max_changes) = max_changes {
+            changes_request.max_changes(max_changes);
+        }
+        let references = [
+            changes_request.created_reference(),
+            changes_request.updated_reference(),
+        ];
+        let changes_call_id = request.last_call_id().unwrap().to_string();
+        let mut get_call_ids = Vec::with_capacity(2);
+        for reference in references {
+            let get_request = request.get_email().ids_ref(reference);
+            if let Some(properties) = &properties {
+                get_request.properties(properties.iter().cloned());
+            }
+            get_call_ids.push(request.last_call_id().unwrap().to_string());
+        }
+
+        let mut response = request.send().await?;
+        let mut take = |call_id: &str| {
+            response
+                .take_method_response_by_id(call_id)
+                .ok_or_else(|| Error::from("Server returned no results"))
+        };
+        let mut changes = take(&changes_call_id)?.unwrap_changes_email()?;
+        let mut emails = Vec::new();
+        for call_id in &get_call_ids {
+            let mut response = take(call_id)?.unwrap_get_email()?;
+            changes.reconcile_not_found(response.not_found());
+            emails.extend(response.take_list());
+        }
+        Ok((changes, emails))
+    }
+
     #[maybe_async::maybe_async]
     pub async fn email_query(
         &self,
@@ -209,6 +613,26 @@ impl Client {
         request.send_single::<QueryResponse>().await
     }
 
+    /// Returns a paginator that fetches the query results `page_size` ids at a time.
+    pub fn email_query_paginated(
+        &self,
+        filter: Option<impl Into<Filter<super::query::Filter>>>,
+        sort: Option<impl IntoIterator<Item = Comparator<super::query::Comparator>>>,
+        page_size: usize,
+    ) -> EmailQueryPaginator<'_> {
+        EmailQueryPaginator {
+            client: self,
+            filter: filter.map(|f| f.into()),
+            sort: sort.map(|s| s.into_iter().collect()),
+            page_size,
+            position: 0,
+            total: None,
+            query_state: None,
+            can_calculate_changes: false,
+            is_done: false,
+        }
+    }
+
     #[maybe_async::maybe_async]
     pub async fn email_query_changes(
         &self,
@@ -223,6 +647,108 @@ impl Client {
         request.send_single::<QueryChangesResponse>().await
     }
 
+    /// Fetches the changes to an email query since `since_query_state` and
+    /// returns them as operations to apply to `ids`, the results currently
+    /// held by the caller. If `up_to_id` is set, changes past that id are
+    /// not requested nor applied.
+    ///
+    /// When the server cannot calculate the changes, the query is run again
+    /// and the returned operations replace `ids`, or the ids up to `up_to_id`,
+    /// with the new results, see [`QueryDiff::is_full_refresh`].
+    #[maybe_async::maybe_async]
+    pub async fn email_query_diff(
+        &self,
+        ids: &[impl AsRef<str>],
+        filter: Option<impl Into<Filter<super::query::Filter>>>,
+        sort: Option<impl IntoIterator<Item = Comparator<super::query::Comparator>>>,
+        since_query_state: impl Into<String>,
+        up_to_id: Option<&str>,
+    ) -> crate::Result<QueryDiff> {
+        let filter = filter.map(|filter| filter.into());
+        let sort = sort.map(|sort| sort.into_iter().collect::<Vec<_>>());
+        let since_query_state = since_query_state.into();
+
+        let mut request = self.build();
+        let query_request = request.query_email_changes(since_query_state.clone());
+        if let Some(filter) = filter.clone() {
+            query_request.filter(filter);
+        }
+        if let Some(sort) = sort.clone() {
+            query_request.sort(sort);
+        }
+        if let Some(up_to_id) = up_to_id {
+            query_request.up_to_id(up_to_id);
+        }
+        match request.send_single::<QueryChangesResponse>().await {
+            Ok(response) => Ok(QueryDiff::new(ids, &response, up_to_id)),
+            Err(err) if err.is_cannot_calculate_changes() => {
+                let window = up_to_id
+                    .and_then(|up_to_id| ids.iter().position(|id| id.as_ref() == up_to_id))
+                    .map_or(ids.len(), |pos| pos + 1);
+
+                let mut request = self.build();
+                let query_request = request.query_email();
+                if let Some(filter) = filter {
+                    query_request.filter(filter);
+                }
+                if let Some(sort) = sort {
+                    query_request.sort(sort);
+                }
+                if up_to_id.is_some() {
+                    query_request.limit(window);
+                }
+                let response = request.send_single::<QueryResponse>().await?;
+                Ok(QueryDiff::replace(
+                    &ids[..window],
+                    since_query_state,
+                    &response,
+                ))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the distinct keywords set on the `sample_size` most recently
+    /// received emails, optionally restricted to a mailbox, in alphabetical
+    /// order. This is a best-effort sample meant for suggesting existing
+    /// labels, keywords only used by older emails will not be included.
+    #[maybe_async::maybe_async]
+    pub async fn used_keywords(
+        &self,
+        mailbox_id: Option<&str>,
+        sample_size: usize,
+    ) -> crate::Result<Vec<String>> {
+        let mut request = self.build();
+        let query_request = request
+            .query_email()
+            .sort([super::query::Comparator::received_at().descending()])
+            .limit(sample_size);
+        if let Some(mailbox_id) = mailbox_id {
+            query_request.filter(super::query::Filter::in_mailbox(mailbox_id));
+        }
+        let ids_ref = query_request.result_reference();
+        request
+            .get_email()
+            .ids_ref(ids_ref)
+            .properties([Property::Keywords]);
+        let get_call_id = request.last_call_id().unwrap().to_string();
+
+        let mut keywords = request
+            .send()
+            .await?
+            .take_method_response_by_id(&get_call_id)
+            .ok_or_else(|| Error::from("Server returned no results"))?
+            .unwrap_get_email()?
+            .list()
+            .iter()
+            .flat_map(|email| email.keywords())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        keywords.sort_unstable();
+        keywords.dedup();
+        Ok(keywords)
+    }
+
     #[maybe_async::maybe_async]
     pub async fn email_parse(
         &self,
@@ -256,7 +782,7 @@ impl Client {
     #[maybe_async::maybe_async]
     pub async fn email_copy<T, U, V, W>(
         &self,
-        from_account_id: impl Into<String>,
+        from_account_id: impl Into<AccountId>,
         id: impl Into<String>,
         mailbox_ids: T,
         keywords: Option<V>,
@@ -289,6 +815,50 @@ impl Client {
             .created(&id)
     }
 
+    /// Moves an email from another account by copying it and destroying the
+    /// original. Returns the copy together with the outcome of destroying the
+    /// original. If `destroy_from_if_in_state` does not match the state of the
+    /// source account, the original is kept and the outcome is a
+    /// `stateMismatch` error, even though the copy was created.
+    #[maybe_async::maybe_async]
+    pub async fn email_move_from_account<T, U>(
+        &self,
+        from_account_id: impl Into<AccountId>,
+        id: impl Into<String>,
+        mailbox_ids: T,
+        destroy_from_if_in_state: Option<impl Into<String>>,
+    ) -> crate::Result<(Email, crate::Result<()>)>
+    where
+        T: IntoIterator<Item = U>,
+        U: Into<String>,
+    {
+        let id = id.into();
+        let mut request = self.build();
+        let copy_request = request.copy_email(from_account_id);
+        copy_request.on_success_destroy_original(true);
+        if let Some(destroy_from_if_in_state) = destroy_from_if_in_state {
+            copy_request.destroy_from_if_in_state(destroy_from_if_in_state);
+        }
+        copy_request.create(id.clone()).mailbox_ids(mailbox_ids);
+        let call_id = request.last_call_id().unwrap().to_string();
+
+        // The implicit Email/set destroying the original shares the call id of
+        // the copy and follows its response.
+        let mut response = request.send().await?;
+        let email = response
+            .take_method_response_by_id(&call_id)
+            .ok_or_else(|| Error::from("Server returned no results"))?
+            .unwrap_copy_email()?
+            .created(&id)?;
+        let destroyed = match response.take_method_response_by_id(&call_id) {
+            Some(response) => response
+                .unwrap_set_email()
+                .and_then(|mut response| response.destroyed(&id)),
+            None => Ok(()),
+        };
+        Ok((email, destroyed))
+    }
+
     #[maybe_async::maybe_async]
     pub async fn search_snippet_get(
         &self,
@@ -305,6 +875,172 @@ impl Client {
     }
 }
 
+pub struct EmailQueryPaginator<'x> {
+    client: &'x Client,
+    filter: Option<Filter<super::query::Filter>>,
+    sort: Option<Vec<Comparator<super::query::Comparator>>>,
+    page_size: usize,
+    position: usize,
+    total: Option<usize>,
+    query_state: Option<String>,
+    can_calculate_changes: bool,
+    is_done: bool,
+}
+
+impl EmailQueryPaginator<'_> {
+    /// Fetches the next page of results, returning `None` once all the results
+    /// have been returned.
+    #[maybe_async::maybe_async]
+    pub async fn next_page(&mut self) -> crate::Result<Option<QueryPage>> {
+        if self.is_done {
+            return Ok(None);
+        }
+
+        let mut request = self.client.build();
+        let query_request = request
+            .query_email()
+            .position(self.position as i32)
+            .limit(self.page_size)
+            .calculate_total(true);
+        if let Some(filter) = &self.filter {
+            query_request.filter(filter.clone());
+        }
+        if let Some(sort) = &self.sort {
+            query_request.sort(sort.iter().cloned());
+        }
+        let mut response = request.send_single::<QueryResponse>().await?;
+
+        let query_state = response.take_query_state();
+        match &self.query_state {
+            Some(current_state) if current_state != &query_state => {
+                self.reset();
+                self.query_state = query_state.into();
+                return Ok(Some(QueryPage::StateChanged));
+            }
+            _ => {
+                self.query_state = query_state.into();
+            }
+        }
+
+        self.can_calculate_changes = response.can_calculate_changes();
+        let ids = response.take_ids();
+        self.position += ids.len();
+        self.total = response.total();
+        self.is_done = ids.is_empty()
+            || match self.total {
+                Some(total) => self.position >= total,
+                None => ids.len() < self.page_size,
+            };
+
+        Ok(Some(QueryPage::Ids(ids)))
+    }
+
+    /// Fetches the changes to the query results since the last page was
+    /// returned. If the server cannot calculate changes for this query, the
+    /// paginator is reset and `None` is returned, so the results need to be
+    /// fetched again using [`EmailQueryPaginator::next_page`].
+    #[maybe_async::maybe_async]
+    pub async fn changes(&mut self) -> crate::Result<Option<QueryChangesResponse>> {
+        let query_state = match &self.query_state {
+            Some(query_state) if self.can_calculate_changes => query_state.clone(),
+            _ => {
+                self.reset();
+                return Ok(None);
+            }
+        };
+
+        let mut request = self.client.build();
+        let changes_request = request.query_email_changes(query_state);
+        if let Some(filter) = &self.filter {
+            changes_request.filter(filter.clone());
+        }
+        if let Some(sort) = &self.sort {
+            changes_request.sort(sort.iter().cloned());
+        }
+        match request.send_single::<QueryChangesResponse>().await {
+            Ok(response) => {
+                self.query_state = response.new_query_state().to_string().into();
+                Ok(Some(response))
+            }
+            Err(Error::Method(err)) if err.error() == &MethodErrorType::CannotCalculateChanges => {
+                self.reset();
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Restarts the pagination from the first page.
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.total = None;
+        self.query_state = None;
+        self.can_calculate_changes = false;
+        self.is_done = false;
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    pub fn query_state(&self) -> Option<&str> {
+        self.query_state.as_deref()
+    }
+}
+
+fn attachments_size(email: &Email<Set>) -> usize {
+    fn size(part: &EmailBodyPart, email: &Email<Set>) -> usize {
+        let value_size = part
+            .part_id
+            .as_ref()
+            .and_then(|part_id| email.body_values.as_ref()?.get(part_id))
+            .map(|value| value.value.len());
+        value_size.or(part.size).unwrap_or(0)
+            + part
+                .sub_parts
+                .iter()
+                .flatten()
+                .map(|part| size(part, email))
+                .sum::<usize>()
+    }
+
+    email
+        .attachments
+        .iter()
+        .flatten()
+        .map(|part| size(part, email))
+        .sum()
+}
+
+fn visit_body_parts(email: &mut Email<Set>, f: &mut impl FnMut(&mut EmailBodyPart)) {
+    fn visit(parts: &mut [EmailBodyPart], f: &mut impl FnMut(&mut EmailBodyPart)) {
+        for part in parts {
+            f(part);
+            if let Some(sub_parts) = &mut part.sub_parts {
+                visit(sub_parts, f);
+            }
+        }
+    }
+
+    for parts in [
+        &mut email.text_body,
+        &mut email.html_body,
+        &mut email.attachments,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        visit(parts, f);
+    }
+    if let Some(body_structure) = &mut email.body_structure {
+        visit(std::slice::from_mut(body_structure.as_mut()), f);
+    }
+}
+
 impl Request<'_> {
     pub fn get_email(&mut self) -> &mut GetRequest<Email<Set>> {
         self.add_method_call(
@@ -379,7 +1115,7 @@ impl Request<'_> {
 
     pub fn copy_email(
         &mut self,
-        from_account_id: impl Into<String>,
+        from_account_id: impl Into<AccountId>,
     ) -> &mut CopyRequest<Email<Set>> {
         self.add_method_call(
             Method::CopyEmail,
@@ -432,3 +1168,876 @@ impl Request<'_> {
         self.send_single().await
     }
 }
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{
+        core::{error::MethodErrorType, query::QueryPage, set::SetObject},
+        email::{Email, EmailBodyPart, EmailPatch},
+        test_server::{block_on, TestRequest, TestResponse, TestServer},
+        Error, Set,
+    };
+
+    type Filter = crate::core::query::Filter<crate::email::query::Filter>;
+
+    #[test]
+    fn read_only_mailbox_rejects_add() {
+        let server = TestServer::start(|_| TestResponse::new(500, ""));
+        let mailbox: crate::mailbox::Mailbox = serde_json::from_value(serde_json::json!({
+            "id": "archive",
+            "name": "Archive",
+            "myRights": {
+                "mayReadItems": true,
+                "mayAddItems": false,
+                "mayRemoveItems": false,
+                "maySetSeen": true,
+                "maySetKeywords": false,
+                "mayCreateChild": false,
+                "mayRename": false,
+                "mayDelete": false,
+                "maySubmit": false
+            }
+        }))
+        .unwrap();
+
+        block_on(async {
+            let client = server.connect().await;
+            assert!(matches!(
+                client.email_set_mailbox_checked("m1", &mailbox, true).await,
+                Err(Error::Forbidden(message)) if message == "mayAddItems not granted on mailbox archive"
+            ));
+            assert!(matches!(
+                client
+                    .email_set_keyword_checked("m1", &mailbox, "$flagged", true)
+                    .await,
+                Err(Error::Forbidden(_))
+            ));
+            assert!(matches!(
+                client.email_destroy_checked("m1", &mailbox).await,
+                Err(Error::Forbidden(_))
+            ));
+        });
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn email_create_uploads_large_body() {
+        let server = TestServer::start(|request| {
+            if request.path.starts_with("/upload/") {
+                TestResponse::json(
+                    200,
+                    serde_json::json!({
+                        "accountId": "a",
+                        "blobId": "b1",
+                        "type": request.header("Content-Type").unwrap(),
+                        "size": request.body.len()
+                    })
+                    .to_string(),
+                )
+            } else {
+                TestResponse::json(
+                    200,
+                    serde_json::json!({
+                        "sessionState": "s1",
+                        "methodResponses": [["Email/set", {
+                            "accountId": "a",
+                            "newState": "e2",
+                            "created": {"c0": {"id": "m1", "blobId": "b2", "threadId": "t1", "size": 1200}}
+                        }, "s0"]]
+                    })
+                    .to_string(),
+                )
+            }
+        });
+
+        let mut email: Email<Set> = SetObject::new(None);
+        email
+            .subject("Report")
+            .text_body(
+                EmailBodyPart::new()
+                    .part_id("text")
+                    .content_type("text/plain"),
+            )
+            .html_body(
+                EmailBodyPart::new()
+                    .part_id("html")
+                    .content_type("text/html"),
+            )
+            .body_value("text".to_string(), "a".repeat(1000))
+            .body_value("html".to_string(), "<p>Short</p>");
+
+        block_on(async {
+            let mut client = server.connect().await;
+            client.set_body_upload_threshold(100);
+            assert_eq!(client.email_create(email).await.unwrap().id(), Some("m1"));
+        });
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("Content-Type"), Some("text/plain"));
+        assert_eq!(requests[0].body, "a".repeat(1000).into_bytes());
+        let request: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        let create = &request["methodCalls"][0][1]["create"]["c0"];
+        assert_eq!(
+            create["textBody"],
+            serde_json::json!([{"blobId": "b1", "type": "text/plain", "charset": "utf-8"}])
+        );
+        assert_eq!(create["htmlBody"][0]["partId"], "html");
+        assert_eq!(
+            create["bodyValues"],
+            serde_json::json!({"html": {"value": "<p>Short</p>"}})
+        );
+    }
+
+    #[test]
+    fn email_create_attachments_too_large() {
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:mail"]["maxSizeAttachmentsPerEmail"] =
+                    100.into()
+            },
+            |_| TestResponse::new(500, ""),
+        );
+        let mut email: Email<Set> = SetObject::new(None);
+        email
+            .mailbox_ids(["drafts"])
+            .attachment(
+                EmailBodyPart::new()
+                    .part_id("a1")
+                    .content_type("text/plain"),
+            )
+            .attachment(
+                EmailBodyPart::new()
+                    .part_id("a2")
+                    .content_type("text/plain"),
+            )
+            .body_value("a1".to_string(), "a".repeat(60))
+            .body_value("a2".to_string(), "b".repeat(60));
+
+        block_on(async {
+            let mut client = server.connect().await;
+            client.set_body_upload_threshold(10);
+            assert!(matches!(
+                client.email_create(email).await,
+                Err(Error::AttachmentsTooLarge {
+                    total: 120,
+                    max: 100
+                })
+            ));
+        });
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn reply_all_recipients() {
+        let email: Email = serde_json::from_value(serde_json::json!({
+            "from": [{"name": "Alice", "email": "alice@example.org"}],
+            "replyTo": [{"name": "Support", "email": "support@example.org"}],
+            "to": [
+                {"email": "me@example.org"},
+                {"email": "bob@example.org"},
+                {"email": "Support@example.org"}
+            ],
+            "cc": [
+                {"email": "carol@example.org"},
+                {"email": "BOB@example.org"},
+                {"email": "me+alias@example.org"},
+                {"email": "dave@example.org"},
+                {"email": "carol@example.org"}
+            ]
+        }))
+        .unwrap();
+
+        let server = TestServer::start(|_| TestResponse::new(500, ""));
+        let client = block_on(server.connect());
+        let (to, cc) =
+            client.reply_all_recipients(&email, ["me@example.org", "me+alias@example.org"]);
+        assert_eq!(
+            to.iter().map(|address| address.email()).collect::<Vec<_>>(),
+            ["support@example.org", "bob@example.org"]
+        );
+        assert_eq!(
+            cc.iter().map(|address| address.email()).collect::<Vec<_>>(),
+            ["carol@example.org", "dave@example.org"]
+        );
+    }
+
+    #[test]
+    fn used_keywords() {
+        let server = TestServer::start(|_| {
+            TestResponse::json(
+                200,
+                serde_json::json!({
+                    "sessionState": "s1",
+                    "methodResponses": [
+                        ["Email/query", {
+                            "accountId": "a",
+                            "queryState": "q1",
+                            "canCalculateChanges": true,
+                            "position": 0,
+                            "ids": ["m1", "m2", "m3"]
+                        }, "s0"],
+                        ["Email/get", {
+                            "accountId": "a",
+                            "state": "e1",
+                            "list": [
+                                {"id": "m1", "keywords": {"$seen": true, "project-x": true}},
+                                {"id": "m2", "keywords": {"invoices": true, "$seen": true}},
+                                {"id": "m3", "keywords": {"project-x": true, "stale": false}}
+                            ],
+                            "notFound": []
+                        }, "s1"]
+                    ]
+                })
+                .to_string(),
+            )
+        });
+
+        let keywords = block_on(async {
+            server
+                .connect()
+                .await
+                .used_keywords(Some("inbox"), 50)
+                .await
+                .unwrap()
+        });
+        assert_eq!(keywords, ["$seen", "invoices", "project-x"]);
+
+        let request: serde_json::Value =
+            serde_json::from_slice(&server.requests()[0].body).unwrap();
+        assert_eq!(
+            request["methodCalls"][0][1],
+            serde_json::json!({
+                "accountId": "a",
+                "filter": {"inMailbox": "inbox"},
+                "sort": [{"property": "receivedAt", "isAscending": false}],
+                "limit": 50
+            })
+        );
+        assert_eq!(
+            request["methodCalls"][1][1]["#ids"],
+            serde_json::json!({"resultOf": "s0", "name": "Email/query", "path": "/ids"})
+        );
+        assert_eq!(
+            request["methodCalls"][1][1]["properties"],
+            serde_json::json!(["keywords"])
+        );
+    }
+
+    #[test]
+    fn used_keywords_reordered_responses() {
+        let server = TestServer::start(|_| {
+            TestResponse::json(
+                200,
+                serde_json::json!({
+                    "sessionState": "s1",
+                    "methodResponses": [
+                        ["Email/get", {
+                            "accountId": "a",
+                            "state": "e1",
+                            "list": [{"id": "m1", "keywords": {"$seen": true}}],
+                            "notFound": []
+                        }, "s1"],
+                        ["Email/query", {
+                            "accountId": "a",
+                            "queryState": "q1",
+                            "canCalculateChanges": true,
+                            "position": 0,
+                            "ids": ["m1"]
+                        }, "s0"]
+                    ]
+                })
+                .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            assert_eq!(client.used_keywords(None, 10).await.unwrap(), ["$seen"]);
+        });
+    }
+
+    fn query_response(
+        request: &TestRequest,
+        query_state: &str,
+        total: usize,
+        can_calculate_changes: bool,
+    ) -> TestResponse {
+        let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let arguments = &request["methodCalls"][0][1];
+        let position = arguments["position"].as_u64().unwrap() as usize;
+        let limit = arguments["limit"].as_u64().unwrap() as usize;
+        let ids = (position..total.min(position + limit))
+            .map(|id| format!("m{}", id))
+            .collect::<Vec<_>>();
+
+        TestResponse::json(
+            200,
+            serde_json::json!({
+                "sessionState": "s1",
+                "methodResponses": [["Email/query", {
+                    "accountId": "a",
+                    "queryState": query_state,
+                    "canCalculateChanges": can_calculate_changes,
+                    "position": position,
+                    "total": total,
+                    "ids": ids,
+                }, "s0"]]
+            })
+            .to_string(),
+        )
+    }
+
+    #[test]
+    fn email_query_paginated() {
+        let server = TestServer::start(|request| query_response(request, "q1", 7, true));
+
+        block_on(async {
+            let client = server.connect().await;
+            let mut paginator = client.email_query_paginated(None::<Filter>, None::<Vec<_>>, 3);
+            let mut pages = Vec::new();
+            while let Some(page) = paginator.next_page().await.unwrap() {
+                pages.push(page);
+            }
+
+            assert_eq!(
+                pages,
+                [
+                    QueryPage::Ids(vec!["m0".into(), "m1".into(), "m2".into()]),
+                    QueryPage::Ids(vec!["m3".into(), "m4".into(), "m5".into()]),
+                    QueryPage::Ids(vec!["m6".into()]),
+                ]
+            );
+            assert_eq!(paginator.total(), Some(7));
+            assert_eq!(paginator.query_state(), Some("q1"));
+        });
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn email_apply_patch() {
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:core"]["maxObjectsInSet"] = 200.into()
+            },
+            |request| {
+                let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let updated = request["methodCalls"][0][1]["update"]
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .map(|id| (id.clone(), serde_json::Value::Null))
+                    .collect::<serde_json::Map<_, _>>();
+                TestResponse::json(
+                    200,
+                    serde_json::json!({
+                        "sessionState": "s1",
+                        "methodResponses": [["Email/set", {
+                            "accountId": "a",
+                            "newState": "e2",
+                            "updated": updated,
+                        }, "s0"]]
+                    })
+                    .to_string(),
+                )
+            },
+        );
+
+        let ids = (0..500).map(|id| format!("m{}", id)).collect::<Vec<_>>();
+        let mut patch = EmailPatch::new();
+        patch.set_keyword("$seen", true).remove_mailbox("inbox");
+        block_on(async {
+            let client = server.connect().await;
+            client.email_apply_patch(&ids, &patch).await.unwrap();
+        });
+
+        let requests = server
+            .requests()
+            .iter()
+            .map(|request| serde_json::from_slice::<serde_json::Value>(&request.body).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|request| request["methodCalls"][0][1]["update"]
+                    .as_object()
+                    .unwrap()
+                    .len())
+                .collect::<Vec<_>>(),
+            [200, 200, 100]
+        );
+        assert_eq!(
+            requests[2]["methodCalls"][0][1]["update"]["m499"],
+            serde_json::json!({"keywords/$seen": true, "mailboxIds/inbox": null})
+        );
+    }
+
+    #[test]
+    fn email_apply_patch_chunk_failure() {
+        let calls = AtomicUsize::new(0);
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:core"]["maxObjectsInSet"] = 2.into()
+            },
+            move |request| {
+                if calls.fetch_add(1, Ordering::Relaxed) % 3 == 1 {
+                    return TestResponse::new(503, "");
+                }
+                let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let updated = request["methodCalls"][0][1]["update"]
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .map(|id| (id.clone(), serde_json::Value::Null))
+                    .collect::<serde_json::Map<_, _>>();
+                TestResponse::json(
+                    200,
+                    serde_json::json!({
+                        "sessionState": "s1",
+                        "methodResponses": [["Email/set", {
+                            "accountId": "a",
+                            "newState": "e2",
+                            "updated": updated,
+                        }, "s0"]]
+                    })
+                    .to_string(),
+                )
+            },
+        );
+
+        let ids = ["m1", "m2", "m3", "m4", "m5"];
+        let mut patch = EmailPatch::new();
+        patch.set_keyword("$seen", true);
+        block_on(async {
+            let client = server.connect().await;
+            let results = client
+                .email_apply_patch_chunked(ids, &patch, false)
+                .await
+                .unwrap();
+            assert!(!results.is_ok());
+            assert_eq!(
+                results
+                    .chunks()
+                    .iter()
+                    .map(|chunk| chunk.is_ok())
+                    .collect::<Vec<_>>(),
+                [true, false, true]
+            );
+            assert_eq!(results.failed_ids().collect::<Vec<_>>(), ["m3", "m4"]);
+            assert_eq!(
+                results.succeeded_ids().collect::<Vec<_>>(),
+                ["m1", "m2", "m5"]
+            );
+            assert!(matches!(
+                results.into_result(),
+                Err(Error::Server { status: 503, .. })
+            ));
+
+            // Failing fast stops at the second chunk.
+            assert!(matches!(
+                client.email_apply_patch_chunked(ids, &patch, true).await,
+                Err(Error::Server { status: 503, .. })
+            ));
+        });
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[test]
+    fn email_move_patches() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let updated = request["methodCalls"][0][1]["update"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(|id| (id.clone(), serde_json::Value::Null))
+                .collect::<serde_json::Map<_, _>>();
+            TestResponse::json(
+                200,
+                serde_json::json!({
+                    "sessionState": "s1",
+                    "methodResponses": [["Email/set", {
+                        "accountId": "a",
+                        "newState": "e2",
+                        "updated": updated,
+                    }, "s0"]]
+                })
+                .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            client
+                .email_move_to_mailbox(["m1", "m2"], "archive")
+                .await
+                .unwrap();
+            client
+                .email_move_between(["m3"], "inbox", "archive")
+                .await
+                .unwrap();
+        });
+
+        let updates = server
+            .requests()
+            .iter()
+            .map(|request| {
+                serde_json::from_slice::<serde_json::Value>(&request.body).unwrap()["methodCalls"]
+                    [0][1]["update"]
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            updates[0],
+            serde_json::json!({
+                "m1": {"mailboxIds": {"archive": true}},
+                "m2": {"mailboxIds": {"archive": true}}
+            })
+        );
+        assert_eq!(
+            updates[1],
+            serde_json::json!({
+                "m3": {"mailboxIds/inbox": null, "mailboxIds/archive": true}
+            })
+        );
+    }
+
+    #[test]
+    fn email_query_diff_cannot_calculate_changes() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let call = &request["methodCalls"][0];
+            let response = match call[0].as_str().unwrap() {
+                "Email/queryChanges" => {
+                    serde_json::json!(["error", {"type": "cannotCalculateChanges"}, "s0"])
+                }
+                "Email/query" => {
+                    assert_eq!(call[1]["filter"]["inMailbox"], "inbox");
+                    serde_json::json!(["Email/query", {
+                        "accountId": "a",
+                        "queryState": "q9",
+                        "canCalculateChanges": true,
+                        "position": 0,
+                        "ids": ["m5", "m1"],
+                        "total": 2
+                    }, "s0"])
+                }
+                method => panic!("unexpected method {}", method),
+            };
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": [response]})
+                    .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let diff = client
+                .email_query_diff(
+                    &["m1", "m2", "m3"],
+                    Some(crate::email::query::Filter::in_mailbox("inbox")),
+                    None::<Vec<_>>,
+                    "q1",
+                    None,
+                )
+                .await
+                .unwrap();
+            assert!(diff.is_full_refresh());
+            assert_eq!(diff.old_query_state(), "q1");
+            assert_eq!(diff.new_query_state(), "q9");
+
+            let mut ids = vec!["m1".to_string(), "m2".to_string(), "m3".to_string()];
+            diff.apply(&mut ids);
+            assert_eq!(ids, ["m5", "m1"]);
+        });
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn email_sync_changes_capped() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let since: usize = request["methodCalls"][0][1]["sinceState"]
+                .as_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": [
+                    ["Email/changes", {
+                        "accountId": "a",
+                        "oldState": since.to_string(),
+                        "newState": (since + 1).to_string(),
+                        "hasMoreChanges": since + 1 < 10,
+                        "created": [format!("m{}", since + 1)],
+                        "updated": [],
+                        "destroyed": if since == 2 { vec!["m1"] } else { vec![] }
+                    }, "s0"]
+                ]})
+                .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let sync = client.email_sync_changes("0", Some(1), 3).await.unwrap();
+            assert!(sync.has_more());
+            assert_eq!(sync.iterations(), 3);
+            assert_eq!(sync.new_state(), "3");
+            assert_eq!(sync.created(), ["m2", "m3"]);
+            assert!(sync.destroyed().is_empty());
+
+            let sync = client
+                .email_sync_changes(sync.new_state(), Some(1), 100)
+                .await
+                .unwrap();
+            assert!(!sync.has_more());
+            assert_eq!(sync.iterations(), 7);
+            assert_eq!(sync.new_state(), "10");
+        });
+        assert_eq!(server.requests().len(), 10);
+    }
+
+    #[test]
+    fn email_sync_changes_cannot_calculate() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let call = &request["methodCalls"][0];
+            let response = match call[0].as_str().unwrap() {
+                "Email/changes" if call[1]["sinceState"] == "1" => serde_json::json!([
+                    "Email/changes",
+                    {
+                        "accountId": "a",
+                        "oldState": "1",
+                        "newState": "2",
+                        "hasMoreChanges": true,
+                        "created": ["m1"],
+                        "updated": [],
+                        "destroyed": []
+                    },
+                    "s0"
+                ]),
+                "Email/changes" => {
+                    serde_json::json!(["error", {"type": "cannotCalculateChanges"}, "s0"])
+                }
+                _ => serde_json::json!([
+                    "Email/get",
+                    {"accountId": "a", "state": "e9", "list": [], "notFound": []},
+                    "s0"
+                ]),
+            };
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": [response]})
+                    .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let sync = client.email_sync_changes("1", None, 10).await.unwrap();
+            assert!(sync.is_full_refresh());
+            assert!(!sync.has_more());
+            assert_eq!(sync.total_changes(), 0);
+            assert_eq!(sync.new_state(), "e9");
+            assert_eq!(
+                client.export_sync_state().state(&crate::TypeState::Email),
+                Some("e9")
+            );
+        });
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn email_changes_fetch_raced_destroy() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let calls = request["methodCalls"].as_array().unwrap();
+            assert_eq!(calls[1][1]["#ids"]["path"], "/created");
+            assert_eq!(calls[2][1]["#ids"]["path"], "/updated");
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": [
+                    ["Email/get", {
+                        "accountId": "a",
+                        "state": "e2",
+                        "list": [{"id": "m2"}],
+                        "notFound": ["m3"]
+                    }, "s2"],
+                    ["Email/changes", {
+                        "accountId": "a",
+                        "oldState": "e1",
+                        "newState": "e2",
+                        "hasMoreChanges": false,
+                        "created": ["m1"],
+                        "updated": ["m2", "m3"],
+                        "destroyed": ["m4"]
+                    }, "s0"],
+                    ["Email/get", {
+                        "accountId": "a",
+                        "state": "e2",
+                        "list": [{"id": "m1"}],
+                        "notFound": []
+                    }, "s1"]
+                ]})
+                .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let (changes, emails) = client
+                .email_changes_fetch("e1", None, Some([crate::email::Property::Id]))
+                .await
+                .unwrap();
+            assert_eq!(changes.created(), ["m1"]);
+            assert_eq!(changes.updated(), ["m2"]);
+            assert_eq!(changes.destroyed(), ["m4", "m3"]);
+            assert_eq!(
+                emails.iter().map(|e| e.id().unwrap()).collect::<Vec<_>>(),
+                ["m1", "m2"]
+            );
+        });
+    }
+
+    #[test]
+    fn email_query_paginated_state_changed() {
+        let calls = AtomicUsize::new(0);
+        let server = TestServer::start(move |request| {
+            let state = if calls.fetch_add(1, Ordering::Relaxed) == 0 {
+                "q1"
+            } else {
+                "q2"
+            };
+            query_response(request, state, 4, true)
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let mut paginator = client.email_query_paginated(None::<Filter>, None::<Vec<_>>, 2);
+            assert!(matches!(
+                paginator.next_page().await.unwrap(),
+                Some(QueryPage::Ids(_))
+            ));
+            assert_eq!(
+                paginator.next_page().await.unwrap(),
+                Some(QueryPage::StateChanged)
+            );
+            assert_eq!(paginator.position(), 0);
+            assert_eq!(
+                paginator.next_page().await.unwrap(),
+                Some(QueryPage::Ids(vec!["m0".into(), "m1".into()]))
+            );
+        });
+    }
+
+    #[test]
+    fn download_email_source() {
+        let server = TestServer::start(|request| {
+            if request.path.starts_with("/download/") {
+                TestResponse::new(200, "Subject: test\r\n\r\nHello")
+            } else {
+                TestResponse::json(
+                    200,
+                    serde_json::json!({
+                        "sessionState": "s1",
+                        "methodResponses": [["Email/get", {
+                            "accountId": "a",
+                            "state": "e1",
+                            "list": [{"id": "m1", "blobId": "b1"}],
+                            "notFound": []
+                        }, "s0"]]
+                    })
+                    .to_string(),
+                )
+            }
+        });
+
+        let source = block_on(async {
+            let client = server.connect().await;
+            client.download_email_source("m1").await.unwrap()
+        });
+        assert_eq!(source, b"Subject: test\r\n\r\nHello");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let get: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(
+            get["methodCalls"][0][1]["properties"],
+            serde_json::json!(["blobId"])
+        );
+        assert!(requests[1].path.starts_with("/download/a/b1/"));
+    }
+
+    #[test]
+    fn email_query_paginated_cannot_calculate_changes() {
+        let server = TestServer::start(|request| query_response(request, "q1", 3, false));
+
+        block_on(async {
+            let client = server.connect().await;
+            let mut paginator = client.email_query_paginated(None::<Filter>, None::<Vec<_>>, 2);
+            paginator.next_page().await.unwrap();
+            assert_eq!(paginator.position(), 2);
+
+            assert!(paginator.changes().await.unwrap().is_none());
+            assert_eq!(paginator.position(), 0);
+            assert_eq!(
+                paginator.next_page().await.unwrap(),
+                Some(QueryPage::Ids(vec!["m0".into(), "m1".into()]))
+            );
+        });
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| {
+            serde_json::from_slice::<serde_json::Value>(&request.body).unwrap()["methodCalls"][0][0]
+                == "Email/query"
+        }));
+    }
+
+    #[test]
+    fn email_move_from_account() {
+        let server = TestServer::start(|_| {
+            TestResponse::json(
+                200,
+                serde_json::json!({
+                    "sessionState": "s1",
+                    "methodResponses": [
+                        ["Email/copy", {
+                            "fromAccountId": "b",
+                            "accountId": "a",
+                            "newState": "e2",
+                            "created": {"m1": {"id": "m9"}}
+                        }, "s0"],
+                        ["error", {"type": "stateMismatch"}, "s0"]
+                    ]
+                })
+                .to_string(),
+            )
+        });
+
+        let (email, destroyed) = block_on(async {
+            let client = server.connect().await;
+            client
+                .email_move_from_account("b", "m1", ["inbox"], "e1".into())
+                .await
+                .unwrap()
+        });
+        assert_eq!(email.id(), Some("m9"));
+        assert!(matches!(
+            destroyed,
+            Err(Error::Method(err)) if err.error() == &MethodErrorType::StateMismatch
+        ));
+
+        let request: serde_json::Value =
+            serde_json::from_slice(&server.requests()[0].body).unwrap();
+        let arguments = &request["methodCalls"][0][1];
+        assert_eq!(arguments["onSuccessDestroyOriginal"], true);
+        assert_eq!(arguments["destroyFromIfInState"], "e1");
+        assert_eq!(arguments["fromAccountId"], "b");
+    }
+}
@@ -13,7 +13,7 @@ use crate::{
     core::{
         request::ResultReference,
         set::{from_timestamp, SetError},
-        RequestParams,
+        AccountId, RequestParams,
     },
     Error,
 };
@@ -26,7 +26,7 @@ use super::{Email, Property};
 #[derive(Debug, Clone, Serialize)]
 pub struct EmailImportRequest {
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
 
     #[serde(rename = "ifInState")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -87,7 +87,7 @@ impl EmailImportRequest {
         }
     }
 
-    pub fn account_id(&mut self, account_id: impl Into<String>) -> &mut Self {
+    pub fn account_id(&mut self, account_id: impl Into<AccountId>) -> &mut Self {
         self.account_id = account_id.into();
         self
     }
@@ -106,6 +106,10 @@ impl EmailImportRequest {
         );
         self.emails.get_mut(&create_id_str).unwrap()
     }
+
+    pub(crate) fn emails(&self) -> impl Iterator<Item = &EmailImport> {
+        self.emails.values()
+    }
 }
 
 impl EmailImport {
@@ -145,6 +149,10 @@ impl EmailImport {
         self
     }
 
+    pub(crate) fn keywords_len(&self) -> usize {
+        self.keywords.len()
+    }
+
     pub fn received_at(&mut self, received_at: i64) -> &mut Self {
         self.received_at = Some(from_timestamp(received_at));
         self
@@ -186,7 +194,58 @@ impl EmailImportResponse {
         self.created.as_ref().map(|map| map.keys())
     }
 
+    /// Returns the imported emails keyed by their import id.
+    pub fn take_created(&mut self) -> AHashMap<String, Email> {
+        self.created.take().unwrap_or_default()
+    }
+
+    pub fn not_created(&self, id: &str) -> Option<&SetError<Property>> {
+        self.not_created.as_ref().and_then(|map| map.get(id))
+    }
+
     pub fn not_created_ids(&self) -> Option<impl Iterator<Item = &String>> {
         self.not_created.as_ref().map(|map| map.keys())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::set::SetErrorType;
+
+    use super::EmailImportResponse;
+
+    #[test]
+    fn import_response() {
+        let mut response: EmailImportResponse = serde_json::from_value(serde_json::json!({
+            "accountId": "a",
+            "oldState": "e1",
+            "newState": "e2",
+            "created": {
+                "i0": {
+                    "id": "m1",
+                    "blobId": "b1",
+                    "threadId": "t1",
+                    "size": 1024,
+                    "receivedAt": "2022-06-01T10:00:00Z"
+                }
+            },
+            "notCreated": {
+                "i1": {"type": "alreadyExists", "existingId": "m0"}
+            }
+        }))
+        .unwrap();
+
+        let error = response.not_created("i1").unwrap();
+        assert_eq!(error.error(), &SetErrorType::AlreadyExists);
+        assert_eq!(error.existing_id(), Some("m0"));
+        assert!(response.created("i1").is_err());
+
+        let created = response.take_created();
+        let email = &created["i0"];
+        assert_eq!(email.id(), Some("m1"));
+        assert_eq!(email.blob_id(), Some("b1"));
+        assert_eq!(email.thread_id(), Some("t1"));
+        assert_eq!(email.size(), 1024);
+        assert_eq!(email.received_at(), Some(1654077600));
+    }
+}
@@ -190,7 +190,14 @@ pub struct Email<State = Get> {
     #[serde(flatten)]
     #[serde(skip_deserializing)]
     #[serde(skip_serializing_if = "Option::is_none")]
-    patch: Option<AHashMap<String, bool>>,
+    patch: Option<AHashMap<String, Option<bool>>>,
+}
+
+/// A patch that can be applied to many emails at once, see
+/// [`Client::email_apply_patch`](crate::client::Client::email_apply_patch).
+#[derive(Debug, Default, Clone)]
+pub struct EmailPatch {
+    patch: AHashMap<String, Option<bool>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -641,6 +648,24 @@ pub enum BodyProperty {
 }
 
 impl BodyProperty {
+    /// The body properties returned by `Email/get` and `Email/parse` when
+    /// none are requested, as listed in RFC 8621. Requesting them explicitly
+    /// gives the same results on servers with different defaults.
+    pub fn spec_default() -> Vec<BodyProperty> {
+        vec![
+            BodyProperty::PartId,
+            BodyProperty::BlobId,
+            BodyProperty::Size,
+            BodyProperty::Name,
+            BodyProperty::Type,
+            BodyProperty::Charset,
+            BodyProperty::Disposition,
+            BodyProperty::Cid,
+            BodyProperty::Language,
+            BodyProperty::Location,
+        ]
+    }
+
     fn parse(value: &str) -> Option<BodyProperty> {
         match value {
             "partId" => Some(BodyProperty::PartId),
@@ -737,6 +762,13 @@ pub struct MailCapabilities {
 
     #[serde(rename = "mayCreateTopLevelMailbox")]
     may_create_top_level_mailbox: bool,
+
+    #[serde(
+        rename = "maxKeywordsPerEmail",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    max_keywords_per_email: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -785,6 +817,8 @@ impl QueryArguments {
 }
 
 impl GetArguments {
+    /// Sets the properties to fetch for each body part. When not set, the
+    /// server returns [`BodyProperty::spec_default`].
     pub fn body_properties(
         &mut self,
         body_properties: impl IntoIterator<Item = BodyProperty>,
@@ -838,6 +872,16 @@ impl MailCapabilities {
     pub fn may_create_top_level_mailbox(&self) -> bool {
         self.may_create_top_level_mailbox
     }
+
+    /// Maximum number of keywords per email, for servers advertising a limit.
+    /// Requests creating or importing emails with more keywords, or replacing
+    /// all the keywords of an email with more, fail with
+    /// [`Error::TooManyKeywords`](crate::Error::TooManyKeywords) before being
+    /// sent. Keywords added with [`Email::keyword`] patches are not checked, as
+    /// the keywords already present on the email are unknown.
+    pub fn max_keywords_per_email(&self) -> Option<usize> {
+        self.max_keywords_per_email
+    }
 }
 
 impl SubmissionCapabilities {
@@ -971,3 +1015,30 @@ impl From<Email> for TestEmail {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::BodyProperty;
+
+    #[test]
+    fn body_properties_spec_default() {
+        assert_eq!(
+            BodyProperty::spec_default()
+                .iter()
+                .map(|property| property.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "partId",
+                "blobId",
+                "size",
+                "name",
+                "type",
+                "charset",
+                "disposition",
+                "cid",
+                "language",
+                "location"
+            ]
+        );
+    }
+}
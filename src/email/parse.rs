@@ -12,13 +12,16 @@
 use serde::{Deserialize, Serialize};
 
 use super::{BodyProperty, Email, Property};
-use crate::{core::RequestParams, Error};
+use crate::{
+    core::{AccountId, RequestParams},
+    Error,
+};
 use ahash::AHashMap;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct EmailParseRequest {
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
 
     #[serde(rename = "blobIds")]
     blob_ids: Vec<String>,
@@ -9,7 +9,7 @@
  * except according to those terms.
  */
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::Serialize;
 
 use crate::{
@@ -152,6 +152,23 @@ pub enum Comparator {
     Cc,
 }
 
+impl Comparator {
+    pub fn property(&self) -> &'static str {
+        match self {
+            Comparator::ReceivedAt => "receivedAt",
+            Comparator::Size => "size",
+            Comparator::From => "from",
+            Comparator::To => "to",
+            Comparator::Subject => "subject",
+            Comparator::SentAt => "sentAt",
+            Comparator::HasKeyword { .. } => "hasKeyword",
+            Comparator::AllInThreadHaveKeyword { .. } => "allInThreadHaveKeyword",
+            Comparator::SomeInThreadHaveKeyword { .. } => "someInThreadHaveKeyword",
+            Comparator::Cc => "cc",
+        }
+    }
+}
+
 impl Filter {
     pub fn in_mailbox(value: impl Into<String>) -> Self {
         Filter::InMailbox {
@@ -159,6 +176,9 @@ impl Filter {
         }
     }
 
+    /// Matches emails that are in at least one mailbox not in `value`. An
+    /// email in both Trash and Inbox matches `in_mailbox_other_than([trash])`,
+    /// use [`Filter::not_in_mailboxes`] to exclude it.
     pub fn in_mailbox_other_than<U, V>(value: U) -> Self
     where
         U: IntoIterator<Item = V>,
@@ -169,6 +189,18 @@ impl Filter {
         }
     }
 
+    /// Matches emails that are in none of the given mailboxes, expressed as a
+    /// `NOT` operator over `inMailbox` conditions. Unlike
+    /// [`Filter::in_mailbox_other_than`], an email that is also in another
+    /// mailbox does not match.
+    pub fn not_in_mailboxes<U, V>(value: U) -> query::Filter<Filter>
+    where
+        U: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        query::Filter::not(value.into_iter().map(Filter::in_mailbox))
+    }
+
     pub fn before(value: i64) -> Self {
         Filter::Before {
             value: from_timestamp(value),
@@ -223,9 +255,22 @@ impl Filter {
         Filter::HasAttachment { value }
     }
 
+    /// Matches any text field of the email. How the value is split into terms
+    /// and which fields are searched is up to the server. Embedded quotes and
+    /// backslashes are escaped, so a value that is already quoted is no longer
+    /// passed through unchanged; use [Filter::text_phrase] to search for an
+    /// exact phrase.
     pub fn text(value: impl Into<String>) -> Self {
         Filter::Text {
-            value: value.into(),
+            value: escape_quotes(value.into()),
+        }
+    }
+
+    /// Matches the exact phrase in any text field of the email, for servers
+    /// supporting quoted phrases in full-text searches.
+    pub fn text_phrase(phrase: &str) -> Self {
+        Filter::Text {
+            value: format!("\"{}\"", escape_quotes(phrase.to_string())),
         }
     }
 
@@ -253,15 +298,17 @@ impl Filter {
         }
     }
 
+    /// Embedded quotes and backslashes are escaped.
     pub fn subject(value: impl Into<String>) -> Self {
         Filter::Subject {
-            value: value.into(),
+            value: escape_quotes(value.into()),
         }
     }
 
+    /// Embedded quotes and backslashes are escaped.
     pub fn body(value: impl Into<String>) -> Self {
         Filter::Body {
-            value: value.into(),
+            value: escape_quotes(value.into()),
         }
     }
 
@@ -302,6 +349,129 @@ impl Filter {
             value: value.into(),
         }
     }
+
+    /// Parses a search box query such as
+    /// `from:alice subject:invoice has:attachment after:2023-01-01`.
+    ///
+    /// Terms are combined with `AND`, terms separated by `OR` are combined with
+    /// `OR` and terms prefixed with `-` are negated. Values containing spaces
+    /// may be quoted, as in `subject:"monthly report"`. The supported keys are:
+    ///
+    /// - `from:`, `to:`, `cc:`, `bcc:`, `subject:` and `body:`
+    /// - `in:` followed by a mailbox id
+    /// - `keyword:` followed by a keyword, such as `keyword:$flagged`
+    /// - `has:attachment`
+    /// - `before:` and `after:` followed by a `YYYY-MM-DD` date in UTC
+    /// - `larger:` and `smaller:` followed by a size in bytes
+    ///
+    /// Bare words, quoted phrases and terms with unknown keys or invalid values
+    /// are searched as `text`.
+    pub fn parse_query(query: &str) -> query::Filter<Filter> {
+        let mut groups: Vec<Vec<query::Filter<Filter>>> = Vec::new();
+        let mut is_or = false;
+
+        for token in tokenize_query(query) {
+            if token == "OR" {
+                is_or = !groups.is_empty();
+                continue;
+            }
+            let filter = match token.strip_prefix('-') {
+                Some(term) if !term.is_empty() => query::Filter::not([parse_term(term)]),
+                _ => parse_term(&token).into(),
+            };
+            match groups.last_mut() {
+                Some(group) if is_or => group.push(filter),
+                _ => groups.push(vec![filter]),
+            }
+            is_or = false;
+        }
+
+        let mut conditions = groups
+            .into_iter()
+            .map(|mut group| {
+                if group.len() == 1 {
+                    group.pop().unwrap()
+                } else {
+                    query::Filter::or(group)
+                }
+            })
+            .collect::<Vec<_>>();
+        if conditions.len() == 1 {
+            conditions.pop().unwrap()
+        } else {
+            query::Filter::and(conditions)
+        }
+    }
+}
+
+fn tokenize_query(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_quotes = false;
+
+    for ch in query.chars() {
+        if ch == '"' {
+            in_quotes = !in_quotes;
+            token.push(ch);
+        } else if ch.is_whitespace() && !in_quotes {
+            if !token.is_empty() {
+                tokens.push(std::mem::take(&mut token));
+            }
+        } else {
+            token.push(ch);
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn parse_term(term: &str) -> Filter {
+    if let Some((key, value)) = term.split_once(':') {
+        let value = unquote(value);
+        let filter = match key.to_ascii_lowercase().as_str() {
+            _ if value.is_empty() => None,
+            "from" => Some(Filter::from(value)),
+            "to" => Some(Filter::to(value)),
+            "cc" => Some(Filter::cc(value)),
+            "bcc" => Some(Filter::bcc(value)),
+            "subject" => Some(Filter::subject(value)),
+            "body" => Some(Filter::body(value)),
+            "in" => Some(Filter::in_mailbox(value)),
+            "keyword" => Some(Filter::has_keyword(value)),
+            "has" if value.eq_ignore_ascii_case("attachment") => Some(Filter::has_attachment(true)),
+            "before" => parse_date(value).map(|value| Filter::Before { value }),
+            "after" => parse_date(value).map(|value| Filter::After { value }),
+            "larger" => value.parse().ok().map(Filter::min_size),
+            "smaller" => value.parse().ok().map(Filter::max_size),
+            _ => None,
+        };
+        if let Some(filter) = filter {
+            return filter;
+        }
+    }
+
+    if term.len() > 1 && term.starts_with('"') && term.ends_with('"') {
+        Filter::text_phrase(unquote(term))
+    } else {
+        Filter::text(term)
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)
+        .map(|date| date.and_utc())
 }
 
 impl Comparator {
@@ -361,3 +531,108 @@ impl QueryObject for Email<Set> {
 
     type Sort = Comparator;
 }
+
+fn escape_quotes(value: String) -> String {
+    if value.contains(['"', '\\']) {
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+
+    #[test]
+    fn parse_query() {
+        assert_eq!(
+            serde_json::to_value(Filter::parse_query(
+                "from:alice subject:\"monthly invoice\" has:attachment after:2023-01-01 report"
+            ))
+            .unwrap(),
+            serde_json::json!({
+                "operator": "AND",
+                "conditions": [
+                    {"from": "alice"},
+                    {"subject": "monthly invoice"},
+                    {"hasAttachment": true},
+                    {"after": "2023-01-01T00:00:00Z"},
+                    {"text": "report"}
+                ]
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(Filter::parse_query("from:alice OR from:bob unknown:key"))
+                .unwrap(),
+            serde_json::json!({
+                "operator": "AND",
+                "conditions": [
+                    {"operator": "OR", "conditions": [{"from": "alice"}, {"from": "bob"}]},
+                    {"text": "unknown:key"}
+                ]
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(Filter::parse_query("\"exact phrase\"")).unwrap(),
+            serde_json::json!({"text": "\"exact phrase\""})
+        );
+    }
+
+    #[test]
+    fn parse_query_negation() {
+        assert_eq!(
+            serde_json::to_value(Filter::parse_query("invoice -from:billing@example.org")).unwrap(),
+            serde_json::json!({
+                "operator": "AND",
+                "conditions": [
+                    {"text": "invoice"},
+                    {"operator": "NOT", "conditions": [{"from": "billing@example.org"}]}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn not_in_mailboxes() {
+        assert_eq!(
+            serde_json::to_value(Filter::not_in_mailboxes(["trash", "junk"])).unwrap(),
+            serde_json::json!({
+                "operator": "NOT",
+                "conditions": [{"inMailbox": "trash"}, {"inMailbox": "junk"}]
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(Filter::in_mailbox_other_than(["trash", "junk"])).unwrap(),
+            serde_json::json!({"inMailboxOtherThan": ["trash", "junk"]})
+        );
+    }
+
+    #[test]
+    fn text_phrase() {
+        assert_eq!(
+            serde_json::to_value(Filter::text_phrase("quarterly report")).unwrap(),
+            serde_json::json!({"text": "\"quarterly report\""})
+        );
+        assert_eq!(
+            serde_json::to_value(Filter::text_phrase("say \"hi\"")).unwrap(),
+            serde_json::json!({"text": "\"say \\\"hi\\\"\""})
+        );
+    }
+
+    #[test]
+    fn escape_quotes() {
+        assert_eq!(
+            serde_json::to_value(Filter::subject("re: \"draft\"")).unwrap(),
+            serde_json::json!({"subject": "re: \\\"draft\\\""})
+        );
+        assert_eq!(
+            serde_json::to_value(Filter::body("C:\\temp")).unwrap(),
+            serde_json::json!({"body": "C:\\\\temp"})
+        );
+        assert_eq!(
+            serde_json::to_value(Filter::text("plain")).unwrap(),
+            serde_json::json!({"text": "plain"})
+        );
+    }
+}
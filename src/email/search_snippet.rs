@@ -11,7 +11,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::core::{query::Filter, request::ResultReference, RequestParams};
+use crate::core::{query::Filter, request::ResultReference, AccountId, RequestParams};
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct SearchSnippet {
@@ -24,7 +24,7 @@ pub struct SearchSnippet {
 #[derive(Debug, Clone, Serialize)]
 pub struct SearchSnippetGetRequest {
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
 
     #[serde(rename = "filter")]
     #[serde(skip_serializing_if = "Option::is_none")]
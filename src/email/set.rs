@@ -10,15 +10,15 @@
  */
 
 use super::{
-    Email, EmailAddress, EmailAddressGroup, EmailBodyPart, EmailBodyValue, EmailHeader, Header,
-    HeaderValue,
+    Email, EmailAddress, EmailAddressGroup, EmailBodyPart, EmailBodyValue, EmailHeader, EmailPatch,
+    Header, HeaderValue,
 };
 use crate::{
     core::{
         request::ResultReference,
         set::{from_timestamp, SetObject},
     },
-    Get, Set,
+    Error, Get, Set,
 };
 use ahash::AHashMap;
 
@@ -43,10 +43,30 @@ impl Email<Set> {
         self.mailbox_ids = None;
         self.patch
             .get_or_insert_with(AHashMap::new)
-            .insert(format!("mailboxIds/{}", mailbox_id), set);
+            .insert(format!("mailboxIds/{}", mailbox_id), set.then_some(true));
         self
     }
 
+    /// Adds the message to a mailbox, keeping its other mailbox memberships.
+    pub fn add_to_mailbox(&mut self, mailbox_id: &str) -> &mut Self {
+        self.mailbox_id(mailbox_id, true)
+    }
+
+    /// Removes the message from a mailbox, keeping its other mailbox memberships.
+    pub fn remove_from_mailbox(&mut self, mailbox_id: &str) -> &mut Self {
+        self.mailbox_id(mailbox_id, false)
+    }
+
+    pub fn apply_patch(&mut self, patch: &EmailPatch) -> &mut Self {
+        self.patch
+            .get_or_insert_with(AHashMap::new)
+            .extend(patch.patch.iter().map(|(k, v)| (k.clone(), *v)));
+        self
+    }
+
+    /// Sets the full list of keywords, replacing any keywords already present
+    /// on the message. Use [`Email::keyword`] to add or remove a single keyword
+    /// while leaving the rest untouched.
     pub fn keywords<T, U>(&mut self, keywords: T) -> &mut Self
     where
         T: IntoIterator<Item = U>,
@@ -56,14 +76,23 @@ impl Email<Set> {
         self
     }
 
+    /// Adds or removes a single keyword using a `keywords/<keyword>` patch,
+    /// preserving any other keywords set on the message.
     pub fn keyword(&mut self, keyword: &str, set: bool) -> &mut Self {
         self.keywords = None;
         self.patch
             .get_or_insert_with(AHashMap::new)
-            .insert(format!("keywords/{}", keyword), set);
+            .insert(format!("keywords/{}", keyword), set.then_some(true));
         self
     }
 
+    /// Number of keywords in the full `keywords` map. Keywords added through
+    /// `keywords/<keyword>` patches are not counted, as the keywords already
+    /// present on the message are unknown.
+    pub(crate) fn keywords_len(&self) -> usize {
+        self.keywords.as_ref().map_or(0, |keywords| keywords.len())
+    }
+
     pub fn message_id<T, U>(&mut self, message_id: T) -> &mut Self
     where
         T: IntoIterator<Item = U>,
@@ -188,15 +217,176 @@ impl Email<Set> {
         self
     }
 
+    /// Attaches `original` as a `message/rfc822` part and sets the subject to the
+    /// original subject prefixed with `Fwd: `. The original must have been
+    /// fetched with its `blobId`.
+    pub fn forward_as_attachment(&mut self, original: &Email<Get>) -> crate::Result<&mut Self> {
+        let blob_id = original
+            .blob_id()
+            .ok_or_else(|| Error::from("The forwarded email has no blobId."))?;
+        let subject = original.subject().unwrap_or_default();
+
+        let mut attachment = EmailBodyPart::new()
+            .blob_id(blob_id)
+            .content_type("message/rfc822");
+        if !subject.is_empty() {
+            attachment = attachment.name(format!("{}.eml", subject));
+        }
+        if subject
+            .get(..4)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("fwd:"))
+        {
+            self.subject(subject);
+        } else {
+            self.subject(format!("Fwd: {}", subject));
+        }
+        Ok(self.attachment(attachment))
+    }
+
     pub fn header(&mut self, header: Header, value: impl Into<HeaderValue>) -> &mut Self {
         self.headers.insert(header, Some(value.into()));
         self
     }
 
+    /// Adds a header in raw form, such as `List-Unsubscribe` or `X-Custom`.
+    /// Adding the same header again keeps all the values, in order. The value
+    /// is used as is, so it should start with a space and be folded if needed.
+    ///
+    /// Returns an error if `name` is not a valid RFC 5322 field name.
+    pub fn raw_header(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> crate::Result<&mut Self> {
+        let name = name.into();
+        if name.is_empty()
+            || !name
+                .bytes()
+                .all(|ch| (33..=126).contains(&ch) && ch != b':')
+        {
+            return Err(Error::Internal(format!("Invalid header name '{}'.", name)));
+        }
+        let value = value.into();
+
+        let all = Header::as_raw(name.as_str(), true);
+        if let Some(Some(HeaderValue::AsTextAll(values))) = self.headers.get_mut(&all) {
+            values.push(value);
+        } else if let Some(Some(HeaderValue::AsText(first))) =
+            self.headers.remove(&Header::as_raw(name.as_str(), false))
+        {
+            self.headers
+                .insert(all, Some(HeaderValue::AsTextAll(vec![first, value])));
+        } else {
+            self.headers.insert(
+                Header::as_raw(name, false),
+                Some(HeaderValue::AsText(value)),
+            );
+        }
+        Ok(self)
+    }
+
     pub fn received_at(&mut self, received_at: i64) -> &mut Self {
         self.received_at = Some(from_timestamp(received_at));
         self
     }
+
+    /// Checks the invariants that the server enforces on emails being
+    /// created, returning a description of each problem found. An empty list
+    /// means that the email is likely to be accepted.
+    pub fn validate(&self) -> Vec<String> {
+        fn collect<'x>(parts: &'x [EmailBodyPart], part_ids: &mut Vec<&'x str>) {
+            for part in parts {
+                if let Some(part_id) = &part.part_id {
+                    part_ids.push(part_id);
+                }
+                if let Some(sub_parts) = &part.sub_parts {
+                    collect(sub_parts, part_ids);
+                }
+            }
+        }
+
+        let mut problems = Vec::new();
+        if self.mailbox_ids_ref.is_none()
+            && !self
+                .mailbox_ids
+                .as_ref()
+                .is_some_and(|ids| ids.values().any(|set| *set))
+        {
+            problems.push("mailboxIds must contain at least one mailbox".to_string());
+        }
+
+        let mut part_ids = Vec::new();
+        for parts in [&self.text_body, &self.html_body, &self.attachments]
+            .into_iter()
+            .flatten()
+        {
+            collect(parts, &mut part_ids);
+        }
+        if let Some(body_structure) = &self.body_structure {
+            if self.text_body.is_some() || self.html_body.is_some() || self.attachments.is_some() {
+                problems.push(
+                    "bodyStructure cannot be combined with textBody, htmlBody or attachments"
+                        .to_string(),
+                );
+            }
+            collect(std::slice::from_ref(body_structure.as_ref()), &mut part_ids);
+        }
+
+        for part_id in &part_ids {
+            if !self
+                .body_values
+                .as_ref()
+                .is_some_and(|values| values.contains_key(*part_id))
+            {
+                problems.push(format!(
+                    "partId {} has no matching bodyValues entry",
+                    part_id
+                ));
+            }
+        }
+        if let Some(body_values) = &self.body_values {
+            let mut unused = body_values
+                .keys()
+                .filter(|id| !part_ids.contains(&id.as_str()))
+                .collect::<Vec<_>>();
+            unused.sort();
+            for id in unused {
+                problems.push(format!(
+                    "bodyValues entry {} is not referenced by any part",
+                    id
+                ));
+            }
+        }
+        problems
+    }
+}
+
+impl EmailPatch {
+    pub fn new() -> Self {
+        EmailPatch::default()
+    }
+
+    pub fn set_keyword(&mut self, keyword: &str, set: bool) -> &mut Self {
+        self.patch
+            .insert(format!("keywords/{}", keyword), set.then_some(true));
+        self
+    }
+
+    pub fn add_mailbox(&mut self, mailbox_id: &str) -> &mut Self {
+        self.patch
+            .insert(format!("mailboxIds/{}", mailbox_id), Some(true));
+        self
+    }
+
+    pub fn remove_mailbox(&mut self, mailbox_id: &str) -> &mut Self {
+        self.patch
+            .insert(format!("mailboxIds/{}", mailbox_id), None);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patch.is_empty()
+    }
 }
 
 impl SetObject for Email<Set> {
@@ -452,3 +642,166 @@ impl EmailHeader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::set::SetObject,
+        email::{Email, EmailBodyPart},
+        Get, Set,
+    };
+
+    #[test]
+    fn validate_create() {
+        let mut email: Email<Set> = Email::new(None);
+        email
+            .mailbox_ids(["inbox"])
+            .body_value("a".to_string(), "Hello")
+            .text_body(EmailBodyPart::new().part_id("a"));
+        assert!(email.validate().is_empty());
+
+        let mut email: Email<Set> = Email::new(None);
+        email
+            .mailbox_ids(["inbox"])
+            .body_structure(EmailBodyPart::new().part_id("a").into());
+        assert_eq!(
+            email.validate(),
+            ["partId a has no matching bodyValues entry"]
+        );
+
+        let mut email: Email<Set> = Email::new(None);
+        email
+            .mailbox_ids(Vec::<String>::new())
+            .body_value("b".to_string(), "Hello");
+        assert_eq!(
+            email.validate(),
+            [
+                "mailboxIds must contain at least one mailbox",
+                "bodyValues entry b is not referenced by any part"
+            ]
+        );
+    }
+
+    #[test]
+    fn created_blob_id() {
+        let mut response: crate::core::response::EmailSetResponse = serde_json::from_str(
+            r#"{
+                "accountId": "a",
+                "oldState": "e1",
+                "newState": "e2",
+                "created": {
+                    "c0": {"id": "m1", "blobId": "B1", "threadId": "T1", "size": 512}
+                }
+            }"#,
+        )
+        .unwrap();
+        let email = response.created("c0").unwrap();
+        assert_eq!(email.id(), Some("m1"));
+        assert_eq!(email.blob_id(), Some("B1"));
+        assert_eq!(email.thread_id(), Some("T1"));
+        assert_eq!(email.size(), 512);
+    }
+
+    #[test]
+    fn keyword_patch() {
+        let mut email: Email<Set> = Email::new(None);
+        email.keyword("$seen", true);
+        assert_eq!(
+            serde_json::to_value(&email).unwrap(),
+            serde_json::json!({"keywords/$seen": true})
+        );
+
+        // Patching a single keyword must not touch the keywords map, otherwise
+        // the server would drop unrelated keywords such as $flagged.
+        let mut email: Email<Set> = Email::new(None);
+        email.keywords(["$flagged"]).keyword("$seen", true);
+        let value = serde_json::to_value(&email).unwrap();
+        assert!(value.get("keywords").is_none());
+        assert_eq!(value["keywords/$seen"], true);
+
+        let mut email: Email<Set> = Email::new(None);
+        email.keyword("$flagged", false);
+        assert_eq!(
+            serde_json::to_value(&email).unwrap(),
+            serde_json::json!({"keywords/$flagged": null})
+        );
+
+        let mut email: Email<Set> = Email::new(None);
+        email.keywords(["$seen"]);
+        assert_eq!(
+            serde_json::to_value(&email).unwrap(),
+            serde_json::json!({"keywords": {"$seen": true}})
+        );
+    }
+
+    #[test]
+    fn mailbox_patch() {
+        let mut email: Email<Set> = Email::new(None);
+        email.add_to_mailbox("a");
+        assert_eq!(
+            serde_json::to_value(&email).unwrap(),
+            serde_json::json!({"mailboxIds/a": true})
+        );
+
+        let mut email: Email<Set> = Email::new(None);
+        email.add_to_mailbox("a").remove_from_mailbox("b");
+        assert_eq!(
+            serde_json::to_value(&email).unwrap(),
+            serde_json::json!({"mailboxIds/a": true, "mailboxIds/b": null})
+        );
+    }
+
+    #[test]
+    fn forward_as_attachment() {
+        let original: Email<Get> = serde_json::from_value(serde_json::json!({
+            "id": "m1",
+            "blobId": "b1",
+            "subject": "Quarterly report"
+        }))
+        .unwrap();
+
+        let mut email: Email<Set> = Email::new(None);
+        email.forward_as_attachment(&original).unwrap();
+        let value = serde_json::to_value(&email).unwrap();
+        assert_eq!(value["subject"], "Fwd: Quarterly report");
+        assert_eq!(
+            value["attachments"],
+            serde_json::json!([{
+                "blobId": "b1",
+                "type": "message/rfc822",
+                "name": "Quarterly report.eml"
+            }])
+        );
+
+        let original: Email<Get> =
+            serde_json::from_value(serde_json::json!({"id": "m1", "subject": "Hi"})).unwrap();
+        assert!(Email::<Set>::new(None)
+            .forward_as_attachment(&original)
+            .is_err());
+    }
+
+    #[test]
+    fn raw_header() {
+        let mut email: Email<Set> = Email::new(None);
+        email
+            .raw_header("List-Unsubscribe", " <mailto:leave@example.org>")
+            .unwrap()
+            .raw_header("X-Custom", " one")
+            .unwrap()
+            .raw_header("X-Custom", " two")
+            .unwrap();
+        let value = serde_json::to_value(&email).unwrap();
+        assert_eq!(
+            value["header:List-Unsubscribe"],
+            " <mailto:leave@example.org>"
+        );
+        assert_eq!(
+            value["header:X-Custom:all"],
+            serde_json::json!([" one", " two"])
+        );
+        assert!(value.get("header:X-Custom").is_none());
+
+        assert!(Email::<Set>::new(None).raw_header("X Custom", "x").is_err());
+        assert!(Email::<Set>::new(None).raw_header("X:Custom", "x").is_err());
+    }
+}
@@ -9,9 +9,13 @@
  * except according to those terms.
  */
 
-use super::{Address, Delivered, DeliveryStatus, Displayed, EmailSubmission, UndoStatus};
+use super::{
+    Address, Delivered, DeliveryStatus, Displayed, EmailSubmission, SmtpReply, SmtpReplyClass,
+    UndoStatus,
+};
 use crate::{core::get::GetObject, Get, Set};
 use ahash::AHashMap;
+use chrono::{DateTime, Utc};
 
 impl EmailSubmission<Get> {
     pub fn id(&self) -> Option<&str> {
@@ -46,6 +50,10 @@ impl EmailSubmission<Get> {
         self.send_at.as_ref().map(|t| t.timestamp())
     }
 
+    pub fn send_at_date(&self) -> Option<&DateTime<Utc>> {
+        self.send_at.as_ref()
+    }
+
     pub fn undo_status(&self) -> Option<&UndoStatus> {
         self.undo_status.as_ref()
     }
@@ -84,6 +92,67 @@ impl Address<Get> {
     }
 }
 
+impl SmtpReply {
+    /// Parses an SMTP reply such as `"250 2.1.5 OK"` into its reply code,
+    /// optional enhanced status code (RFC 3463) and text. Only the first line
+    /// of multiline replies is considered.
+    pub fn parse(reply: &str) -> Option<SmtpReply> {
+        let line = reply.lines().next()?.trim();
+        let code = line.get(..3)?;
+        if !matches!(code.as_bytes()[0], b'2'..=b'5') || !code.bytes().all(|ch| ch.is_ascii_digit())
+        {
+            return None;
+        }
+        let code = code.parse().ok()?;
+
+        let rest = match line.as_bytes().get(3) {
+            Some(b' ' | b'-') => line[4..].trim_start(),
+            None => "",
+            _ => return None,
+        };
+        let (enhanced_code, text) = match rest.split_once(' ') {
+            Some((status, text)) if is_enhanced_code(status) => (Some(status), text),
+            None if is_enhanced_code(rest) => (Some(rest), ""),
+            _ => (None, rest),
+        };
+
+        Some(SmtpReply {
+            code,
+            enhanced_code: enhanced_code.map(|c| c.to_string()),
+            text: text.to_string(),
+        })
+    }
+
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    pub fn enhanced_code(&self) -> Option<&str> {
+        self.enhanced_code.as_deref()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn class(&self) -> SmtpReplyClass {
+        match self.code / 100 {
+            2 => SmtpReplyClass::Success,
+            3 => SmtpReplyClass::Intermediate,
+            4 => SmtpReplyClass::TransientFailure,
+            _ => SmtpReplyClass::PermanentFailure,
+        }
+    }
+}
+
+fn is_enhanced_code(status: &str) -> bool {
+    let mut parts = status.split('.');
+    matches!(parts.next(), Some("2" | "4" | "5"))
+        && parts.clone().count() == 2
+        && parts
+            .all(|part| (1..=3).contains(&part.len()) && part.bytes().all(|ch| ch.is_ascii_digit()))
+}
+
 impl DeliveryStatus {
     #[cfg(feature = "debug")]
     pub fn new(smtp_reply: impl Into<String>, delivered: Delivered, displayed: Displayed) -> Self {
@@ -98,6 +167,10 @@ impl DeliveryStatus {
         &self.smtp_reply
     }
 
+    pub fn parsed_smtp_reply(&self) -> Option<SmtpReply> {
+        SmtpReply::parse(&self.smtp_reply)
+    }
+
     pub fn delivered(&self) -> &Delivered {
         &self.delivered
     }
@@ -114,3 +187,57 @@ impl GetObject for EmailSubmission<Set> {
 impl GetObject for EmailSubmission<Get> {
     type GetArguments = ();
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::{
+        email_submission::{EmailSubmission, SmtpReply, SmtpReplyClass, UndoStatus},
+        Get,
+    };
+
+    #[test]
+    fn deserialize_scheduled_send_at() {
+        let send_at = (Utc::now() + Duration::days(1))
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string();
+        let submission: EmailSubmission<Get> = serde_json::from_value(serde_json::json!({
+            "id": "es1",
+            "emailId": "m1",
+            "sendAt": send_at,
+            "undoStatus": "pending"
+        }))
+        .unwrap();
+
+        let date = submission.send_at_date().unwrap();
+        assert!(*date > Utc::now());
+        assert_eq!(date.format("%Y-%m-%dT%H:%M:%SZ").to_string(), send_at);
+        assert_eq!(submission.send_at(), Some(date.timestamp()));
+        assert_eq!(submission.undo_status(), Some(&UndoStatus::Pending));
+    }
+
+    #[test]
+    fn parse_smtp_reply() {
+        let reply = SmtpReply::parse("250 2.1.5 OK").unwrap();
+        assert_eq!(reply.code(), 250);
+        assert_eq!(reply.enhanced_code(), Some("2.1.5"));
+        assert_eq!(reply.text(), "OK");
+        assert_eq!(reply.class(), SmtpReplyClass::Success);
+
+        let reply = SmtpReply::parse("421 Service not available").unwrap();
+        assert_eq!(reply.code(), 421);
+        assert_eq!(reply.enhanced_code(), None);
+        assert_eq!(reply.text(), "Service not available");
+        assert_eq!(reply.class(), SmtpReplyClass::TransientFailure);
+
+        let reply = SmtpReply::parse("550-5.1.1 User unknown\r\n550 5.1.1 See docs").unwrap();
+        assert_eq!(reply.code(), 550);
+        assert_eq!(reply.enhanced_code(), Some("5.1.1"));
+        assert_eq!(reply.text(), "User unknown");
+        assert_eq!(reply.class(), SmtpReplyClass::PermanentFailure);
+
+        assert_eq!(SmtpReply::parse("OK"), None);
+        assert_eq!(SmtpReply::parse("999 Huh"), None);
+    }
+}
@@ -124,6 +124,21 @@ pub struct DeliveryStatus {
     displayed: Displayed,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpReply {
+    code: u16,
+    enhanced_code: Option<String>,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpReplyClass {
+    Success,
+    Intermediate,
+    TransientFailure,
+    PermanentFailure,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Delivered {
     #[serde(rename = "queued")]
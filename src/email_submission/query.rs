@@ -60,6 +60,8 @@ pub enum Comparator {
     ThreadId,
     #[serde(rename = "sentAt")]
     SentAt,
+    #[serde(rename = "sendAt")]
+    SendAt,
 }
 
 impl Filter {
@@ -122,6 +124,10 @@ impl Comparator {
     pub fn sent_at() -> query::Comparator<Comparator> {
         query::Comparator::new(Comparator::SentAt)
     }
+
+    pub fn send_at() -> query::Comparator<Comparator> {
+        query::Comparator::new(Comparator::SendAt)
+    }
 }
 
 impl QueryObject for EmailSubmission<Set> {
@@ -131,3 +137,54 @@ impl QueryObject for EmailSubmission<Set> {
 
     type Sort = Comparator;
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{query::QueryRequest, RequestParams},
+        email_submission::EmailSubmission,
+        Method, Set,
+    };
+
+    use super::{Comparator, Filter};
+
+    #[test]
+    fn query_sent_at() {
+        let mut request: QueryRequest<EmailSubmission<Set>> =
+            QueryRequest::new(RequestParams::new("a", Method::QueryEmailSubmission, 0));
+        request
+            .sort([Comparator::sent_at().descending()])
+            .position(20)
+            .limit(10)
+            .calculate_total(true);
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "accountId": "a",
+                "sort": [{"property": "sentAt", "isAscending": false}],
+                "position": 20,
+                "limit": 10,
+                "calculateTotal": true
+            })
+        );
+    }
+
+    #[test]
+    fn query_scheduled_send_at() {
+        let mut request: QueryRequest<EmailSubmission<Set>> =
+            QueryRequest::new(RequestParams::new("a", Method::QueryEmailSubmission, 0));
+        request
+            .filter(Filter::after(1767225600))
+            .sort([Comparator::send_at()]);
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "accountId": "a",
+                "filter": {"after": "2026-01-01T00:00:00Z"},
+                "sort": [{"property": "sendAt", "isAscending": true}]
+            })
+        );
+    }
+}
@@ -10,6 +10,7 @@
  */
 
 pub mod parser;
+#[cfg(feature = "event-source")]
 pub mod stream;
 
 use crate::{core::session::URLParser, TypeState};
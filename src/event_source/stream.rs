@@ -25,7 +25,7 @@ impl Client {
     ) -> crate::Result<impl Stream<Item = crate::Result<Changes>> + Unpin> {
         let mut event_source_url = String::with_capacity(self.session().event_source_url().len());
 
-        for part in self.event_source_url() {
+        for part in self.event_source_url().iter() {
             match part {
                 URLPart::Value(value) => {
                     event_source_url.push_str(value);
@@ -69,19 +69,27 @@ impl Client {
             );
         }
 
-        let mut stream = Client::handle_error(
-            reqwest::Client::builder()
-                .connect_timeout(self.timeout())
-                .danger_accept_invalid_certs(self.accept_invalid_certs)
-                .redirect(self.redirect_policy())
-                .default_headers(headers)
-                .build()?
-                .get(event_source_url)
-                .send()
-                .await?,
-        )
-        .await?
-        .bytes_stream();
+        let mut builder = reqwest::Client::builder();
+        if !self.timeout().is_zero() {
+            builder = builder.connect_timeout(self.timeout());
+        }
+
+        let response = builder
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .redirect(self.redirect_policy())
+            .default_headers(headers)
+            .build()?
+            .get(event_source_url)
+            .send();
+        let response = if !self.event_source_timeout().is_zero() {
+            tokio::time::timeout(self.event_source_timeout(), response)
+                .await
+                .map_err(|_| crate::Error::Timeout)??
+        } else {
+            response.await?
+        };
+
+        let mut stream = Client::handle_error(response).await?.bytes_stream();
         let mut parser = EventParser::default();
 
         Ok(Box::pin(async_stream::stream! {
@@ -107,4 +115,101 @@ impl Client {
             }
         }))
     }
+
+    /// Streams the Email state of the default account, skipping changes that
+    /// repeat the last state seen.
+    pub async fn email_state_stream(
+        &self,
+    ) -> crate::Result<impl Stream<Item = crate::Result<String>> + Unpin> {
+        let mut stream = self
+            .event_source(Some([TypeState::Email]), false, None, None)
+            .await?;
+        let account_id = self.default_account_id().to_string();
+        let mut last_state = None;
+
+        Ok(Box::pin(async_stream::stream! {
+            while let Some(changes) = stream.next().await {
+                match changes {
+                    Ok(mut changes) => {
+                        let state = changes
+                            .account_changes(&account_id)
+                            .and_then(|mut changes| changes.remove(&TypeState::Email));
+                        if let Some(state) = state {
+                            if last_state.as_ref() != Some(&state) {
+                                last_state = Some(state.clone());
+                                yield Ok(state);
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        yield Err(err);
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+
+    use crate::{
+        test_server::{block_on, TestResponse, TestServer},
+        Error, TypeState,
+    };
+
+    #[test]
+    fn event_source_timeout() {
+        let server = TestServer::start(|_| {
+            std::thread::sleep(Duration::from_millis(500));
+            TestResponse::new(200, "")
+        });
+
+        let result = block_on(async {
+            let mut client = server.connect().await;
+            client.set_event_source_timeout(Duration::from_millis(50));
+            client
+                .event_source(None::<Vec<TypeState>>, false, None, None)
+                .await
+                .map(|_| ())
+        });
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn email_state_stream_dedup() {
+        let server = TestServer::start(|_| {
+            let frame = |email_state: &str, mailbox_state: &str| {
+                format!(
+                    "event: state\ndata: {}\n\n",
+                    serde_json::json!({
+                        "@type": "StateChange",
+                        "changed": {"a": {"Email": email_state, "Mailbox": mailbox_state}}
+                    })
+                )
+            };
+            TestResponse::new(
+                200,
+                [frame("e1", "m1"), frame("e1", "m2"), frame("e2", "m2")].concat(),
+            )
+            .header("Content-Type", "text/event-stream")
+        });
+
+        let states = block_on(async {
+            let client = server.connect().await;
+            client
+                .email_state_stream()
+                .await
+                .unwrap()
+                .map(|state| state.unwrap())
+                .collect::<Vec<_>>()
+                .await
+        });
+        assert_eq!(states, ["e1", "e2"]);
+        assert!(server.requests()[0].path.contains("types=Email"));
+    }
 }
@@ -18,7 +18,7 @@ use crate::{
         response::{IdentityGetResponse, IdentitySetResponse},
         set::{SetObject, SetRequest},
     },
-    Get, Method, Set,
+    Error, Get, Method, Set,
 };
 
 use super::{Identity, Property};
@@ -30,6 +30,13 @@ impl Client {
         name: impl Into<String>,
         email: impl Into<String>,
     ) -> crate::Result<Identity> {
+        let email = email.into();
+        if email.is_empty() {
+            return Err(Error::Internal(
+                "An email address is required to create an identity.".to_string(),
+            ));
+        }
+
         let mut request = self.build();
         let id = request
             .set_identity()
@@ -71,6 +78,23 @@ impl Client {
             .map(|mut r| r.take_list().pop())
     }
 
+    /// Returns all the identities of the account with all their properties.
+    ///
+    /// JMAP does not link identities to mailboxes: emails sent with any
+    /// identity are usually stored in the mailbox with the
+    /// [`Role::Sent`](crate::mailbox::Role::Sent) role, which can be found with
+    /// [`Client::mailbox_query`] and the
+    /// [`Filter::role`](crate::mailbox::query::Filter::role) filter.
+    #[maybe_async::maybe_async]
+    pub async fn identities(&self) -> crate::Result<Vec<Identity>> {
+        let mut request = self.build();
+        request.get_identity();
+        request
+            .send_single::<IdentityGetResponse>()
+            .await
+            .map(|mut r| r.take_list())
+    }
+
     #[maybe_async::maybe_async]
     pub async fn identity_changes(
         &self,
@@ -125,3 +149,71 @@ impl Request<'_> {
         self.send_single().await
     }
 }
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::test_server::{block_on, TestResponse, TestServer};
+
+    #[test]
+    fn identities() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(request["methodCalls"][0][0], "Identity/get");
+            assert!(request["methodCalls"][0][1].get("ids").is_none());
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": [
+                    ["Identity/get", {
+                        "accountId": "a",
+                        "state": "i1",
+                        "list": [
+                            {
+                                "id": "i1",
+                                "name": "John Doe",
+                                "email": "jdoe@example.org",
+                                "replyTo": [{"name": "Support", "email": "support@example.org"}],
+                                "bcc": [{"name": null, "email": "archive@example.org"}],
+                                "textSignature": "-- John",
+                                "htmlSignature": "",
+                                "mayDelete": false
+                            },
+                            {
+                                "id": "i2",
+                                "name": "",
+                                "email": "*@example.org",
+                                "replyTo": null,
+                                "bcc": null,
+                                "textSignature": "",
+                                "htmlSignature": "",
+                                "mayDelete": true
+                            }
+                        ],
+                        "notFound": []
+                    }, "s0"]
+                ]})
+                .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            let identities = client.identities().await.unwrap();
+            assert_eq!(identities.len(), 2);
+
+            let identity = &identities[0];
+            assert_eq!(identity.name(), Some("John Doe"));
+            assert_eq!(identity.email(), Some("jdoe@example.org"));
+            assert_eq!(
+                identity.reply_to().unwrap()[0].email(),
+                "support@example.org"
+            );
+            assert_eq!(identity.bcc().unwrap()[0].email(), "archive@example.org");
+            assert_eq!(identity.text_signature(), Some("-- John"));
+            assert!(!identity.may_delete());
+
+            assert_eq!(identities[1].email(), Some("*@example.org"));
+            assert!(identities[1].reply_to().is_none());
+            assert!(identities[1].may_delete());
+        });
+    }
+}
@@ -26,27 +26,29 @@ impl Identity<Set> {
 
     pub fn bcc<T, U>(&mut self, bcc: Option<T>) -> &mut Self
     where
-        T: Iterator<Item = U>,
+        T: IntoIterator<Item = U>,
         U: Into<EmailAddress>,
     {
-        self.bcc = bcc.map(|s| s.map(|s| s.into()).collect());
+        self.bcc = bcc.map(|s| s.into_iter().map(|s| s.into()).collect());
         self
     }
 
     pub fn reply_to<T, U>(&mut self, reply_to: Option<T>) -> &mut Self
     where
-        T: Iterator<Item = U>,
+        T: IntoIterator<Item = U>,
         U: Into<EmailAddress>,
     {
-        self.reply_to = reply_to.map(|s| s.map(|s| s.into()).collect());
+        self.reply_to = reply_to.map(|s| s.into_iter().map(|s| s.into()).collect());
         self
     }
 
+    /// Sets the plain text signature, used when composing plain text messages.
     pub fn text_signature(&mut self, text_signature: impl Into<String>) -> &mut Self {
         self.text_signature = Some(text_signature.into());
         self
     }
 
+    /// Sets the HTML signature, used when composing HTML messages.
     pub fn html_signature(&mut self, html_signature: impl Into<String>) -> &mut Self {
         self.html_signature = Some(html_signature.into());
         self
@@ -87,3 +89,35 @@ impl SetObject for Identity<Get> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{core::set::SetObject, identity::Identity, Set};
+
+    #[test]
+    fn serialize_signatures() {
+        let mut identity: Identity<Set> = Identity::new(None);
+        identity
+            .name("John Doe")
+            .email("jdoe@example.org")
+            .reply_to(Some([(
+                "Replies".to_string(),
+                "replies@example.org".to_string(),
+            )]))
+            .bcc(Some(vec!["archive@example.org".to_string()]))
+            .text_signature("-- \nJohn")
+            .html_signature("<p>-- <br>John</p>");
+
+        assert_eq!(
+            serde_json::to_value(&identity).unwrap(),
+            serde_json::json!({
+                "name": "John Doe",
+                "email": "jdoe@example.org",
+                "replyTo": [{"name": "Replies", "email": "replies@example.org"}],
+                "bcc": [{"name": null, "email": "archive@example.org"}],
+                "textSignature": "-- \nJohn",
+                "htmlSignature": "<p>-- <br>John</p>"
+            })
+        );
+    }
+}
@@ -186,7 +186,6 @@ pub mod client;
 pub mod core;
 pub mod email;
 pub mod email_submission;
-#[cfg(feature = "async")]
 pub mod event_source;
 pub mod identity;
 pub mod mailbox;
@@ -196,9 +195,13 @@ pub mod sieve;
 pub mod thread;
 pub mod vacation_response;
 
+use crate::core::error::JMAPError;
 use crate::core::error::MethodError;
+use crate::core::error::MethodErrorType;
 use crate::core::error::ProblemDetails;
+use crate::core::error::ProblemType;
 use crate::core::set::SetError;
+use crate::core::set::SetErrorType;
 use ahash::AHashMap;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
@@ -206,6 +209,9 @@ use std::fmt::Display;
 #[cfg(feature = "websockets")]
 pub mod client_ws;
 
+#[cfg(all(test, feature = "async"))]
+pub(crate) mod test_server;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
 pub enum URI {
     #[serde(rename = "urn:ietf:params:jmap:core")]
@@ -331,6 +337,53 @@ pub enum Method {
     Error,
 }
 
+impl AsRef<str> for Method {
+    fn as_ref(&self) -> &str {
+        match self {
+            Method::Echo => "Core/echo",
+            Method::CopyBlob => "Blob/copy",
+            Method::GetPushSubscription => "PushSubscription/get",
+            Method::SetPushSubscription => "PushSubscription/set",
+            Method::GetMailbox => "Mailbox/get",
+            Method::ChangesMailbox => "Mailbox/changes",
+            Method::QueryMailbox => "Mailbox/query",
+            Method::QueryChangesMailbox => "Mailbox/queryChanges",
+            Method::SetMailbox => "Mailbox/set",
+            Method::GetThread => "Thread/get",
+            Method::ChangesThread => "Thread/changes",
+            Method::GetEmail => "Email/get",
+            Method::ChangesEmail => "Email/changes",
+            Method::QueryEmail => "Email/query",
+            Method::QueryChangesEmail => "Email/queryChanges",
+            Method::SetEmail => "Email/set",
+            Method::CopyEmail => "Email/copy",
+            Method::ImportEmail => "Email/import",
+            Method::ParseEmail => "Email/parse",
+            Method::GetSearchSnippet => "SearchSnippet/get",
+            Method::GetIdentity => "Identity/get",
+            Method::ChangesIdentity => "Identity/changes",
+            Method::SetIdentity => "Identity/set",
+            Method::GetEmailSubmission => "EmailSubmission/get",
+            Method::ChangesEmailSubmission => "EmailSubmission/changes",
+            Method::QueryEmailSubmission => "EmailSubmission/query",
+            Method::QueryChangesEmailSubmission => "EmailSubmission/queryChanges",
+            Method::SetEmailSubmission => "EmailSubmission/set",
+            Method::GetVacationResponse => "VacationResponse/get",
+            Method::SetVacationResponse => "VacationResponse/set",
+            Method::GetSieveScript => "SieveScript/get",
+            Method::SetSieveScript => "SieveScript/set",
+            Method::QuerySieveScript => "SieveScript/query",
+            Method::ValidateSieveScript => "SieveScript/validate",
+            Method::GetPrincipal => "Principal/get",
+            Method::ChangesPrincipal => "Principal/changes",
+            Method::QueryPrincipal => "Principal/query",
+            Method::QueryChangesPrincipal => "Principal/queryChanges",
+            Method::SetPrincipal => "Principal/set",
+            Method::Error => "error",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Clone)]
 pub enum TypeState {
     Mailbox,
@@ -363,12 +416,29 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
     Transport(reqwest::Error),
-    Parse(serde_json::Error),
+    Json(serde_json::Error),
     Internal(String),
     Problem(Box<ProblemDetails>),
-    Server(String),
+    Server {
+        status: u16,
+        body: String,
+    },
     Method(MethodError),
     Set(SetError<String>),
+    Io(std::io::Error),
+    UnsupportedSort(String),
+    TooManyKeywords(usize),
+    Forbidden(String),
+    Unauthorized,
+    BlobTooLarge(usize),
+    AttachmentsTooLarge {
+        total: usize,
+        max: usize,
+    },
+    InvalidReference(String),
+    Timeout,
+    CircuitOpen,
+    SieveParse(sieve::validate::SieveParseError),
     #[cfg(feature = "websockets")]
     WebSocket(tokio_tungstenite::tungstenite::error::Error),
 }
@@ -383,7 +453,7 @@ impl From<reqwest::Error> for Error {
 
 impl From<serde_json::Error> for Error {
     fn from(e: serde_json::Error) -> Self {
-        Error::Parse(e)
+        Error::Json(e)
     }
 }
 
@@ -405,6 +475,12 @@ impl From<SetError<String>> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
 impl From<&str> for Error {
     fn from(s: &str) -> Self {
         Error::Internal(s.to_string())
@@ -418,16 +494,72 @@ impl From<tokio_tungstenite::tungstenite::error::Error> for Error {
     }
 }
 
+impl Error {
+    /// Returns `true` if the server could not calculate the changes since the
+    /// given state, in which case the data has to be fetched again.
+    pub fn is_cannot_calculate_changes(&self) -> bool {
+        matches!(self, Error::Method(err) if err.error() == &MethodErrorType::CannotCalculateChanges)
+    }
+
+    /// Returns `true` if the error was caused by the size of a request or of
+    /// an object within it, in which case the request may succeed once split
+    /// into smaller ones or once the object is reduced.
+    pub fn is_retryable_after_shrinking(&self) -> bool {
+        match self {
+            Error::Problem(problem) => {
+                matches!(problem.error(), ProblemType::JMAP(JMAPError::Limit))
+                    && matches!(
+                        problem.limit(),
+                        Some(
+                            "maxSizeRequest"
+                                | "maxCallsInRequest"
+                                | "maxObjectsInGet"
+                                | "maxObjectsInSet"
+                        )
+                    )
+            }
+            Error::Server { status, .. } => *status == 413,
+            Error::Method(err) => err.error() == &MethodErrorType::RequestTooLarge,
+            Error::Set(err) => err.error() == &SetErrorType::TooLarge,
+            _ => false,
+        }
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::Transport(e) => write!(f, "Transport error: {}", e),
-            Error::Parse(e) => write!(f, "Parse error: {}", e),
+            Error::Json(e) => write!(f, "JSON error: {}", e),
             Error::Internal(e) => write!(f, "Internal error: {}", e),
             Error::Problem(e) => write!(f, "Request failed: {}", e),
-            Error::Server(e) => write!(f, "Server failed: {}", e),
+            Error::Server { status, body } if body.is_empty() => {
+                write!(f, "Server failed with status {}", status)
+            }
+            Error::Server { status, body } => {
+                write!(f, "Server failed with status {}: {}", status, body)
+            }
             Error::Method(e) => write!(f, "Request failed: {}", e),
             Error::Set(e) => write!(f, "Set failed: {}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::UnsupportedSort(e) => write!(f, "Unsupported sort property: {}", e),
+            Error::TooManyKeywords(e) => {
+                write!(f, "Too many keywords, the server allows at most {}", e)
+            }
+            Error::Forbidden(e) => write!(f, "Forbidden: {}", e),
+            Error::Unauthorized => write!(f, "Unauthorized, the credentials were rejected"),
+            Error::BlobTooLarge(e) => {
+                write!(f, "Blob too large, the server allows at most {} bytes", e)
+            }
+            Error::AttachmentsTooLarge { total, max } => write!(
+                f,
+                "Attachments too large, {} bytes exceed the server limit of {} bytes",
+                total, max
+            ),
+            Error::InvalidReference(e) => write!(f, "Invalid result reference: {}", e),
+            Error::Timeout => write!(f, "Timed out"),
+            Error::CircuitOpen => write!(f, "Circuit breaker open, the server is failing"),
+            Error::SieveParse(e) => write!(f, "Invalid Sieve script: {}", e),
             #[cfg(feature = "websockets")]
             Error::WebSocket(e) => write!(f, "WebSockets error: {}", e),
         }
@@ -446,3 +578,116 @@ impl Display for TypeState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{
+            error::{JMAPError, MethodError, MethodErrorType, ProblemDetails, ProblemType},
+            set::SetError,
+        },
+        Error,
+    };
+
+    #[test]
+    fn size_errors() {
+        let problem: ProblemDetails = serde_json::from_str(
+            r#"{"type": "urn:ietf:params:jmap:error:limit",
+                "status": 400,
+                "limit": "maxSizeRequest"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            problem.error(),
+            ProblemType::JMAP(JMAPError::Limit)
+        ));
+        let limit = Error::from(problem);
+        assert_eq!(
+            limit.to_string(),
+            "Request failed: Limit maxSizeRequest (status 400)"
+        );
+        assert!(limit.is_retryable_after_shrinking());
+
+        let set_error: SetError<String> =
+            serde_json::from_str(r#"{"type": "tooLarge", "properties": ["bodyValues"]}"#).unwrap();
+        let too_large = Error::from(set_error);
+        assert!(matches!(&too_large, Error::Set(_)));
+        assert!(too_large.is_retryable_after_shrinking());
+
+        let problem: ProblemDetails = serde_json::from_str(
+            r#"{"type": "urn:ietf:params:jmap:error:limit", "limit": "maxConcurrentRequests"}"#,
+        )
+        .unwrap();
+        assert!(!Error::from(problem).is_retryable_after_shrinking());
+        let set_error: SetError<String> = serde_json::from_str(r#"{"type": "overQuota"}"#).unwrap();
+        assert!(!Error::from(set_error).is_retryable_after_shrinking());
+    }
+
+    #[test]
+    fn error_display() {
+        let transport = reqwest::Client::new().get("not a url").build().unwrap_err();
+        assert!(Error::from(transport)
+            .to_string()
+            .starts_with("Transport error: "));
+        assert!(Error::from(serde_json::from_str::<u32>("x").unwrap_err())
+            .to_string()
+            .starts_with("JSON error: "));
+        assert_eq!(
+            Error::from("oops").to_string(),
+            "Internal error: oops".to_string()
+        );
+        assert_eq!(
+            Error::from(ProblemDetails::new(
+                ProblemType::Other("urn:example:error".to_string()),
+                Some(400),
+                Some("Bad request".to_string()),
+                None,
+                None,
+                None,
+            ))
+            .to_string(),
+            "Request failed: urn:example:error (status 400): Bad request"
+        );
+        assert_eq!(
+            Error::Server {
+                status: 502,
+                body: String::new()
+            }
+            .to_string(),
+            "Server failed with status 502"
+        );
+        assert_eq!(
+            Error::Server {
+                status: 500,
+                body: "boom".to_string()
+            }
+            .to_string(),
+            "Server failed with status 500: boom"
+        );
+        assert_eq!(
+            Error::from(MethodError {
+                p_type: MethodErrorType::Forbidden,
+                description: None,
+            })
+            .to_string(),
+            "Request failed: Forbidden"
+        );
+        let set_error: SetError<String> =
+            serde_json::from_str(r#"{"type": "forbidden", "description": "No"}"#).unwrap();
+        assert_eq!(
+            Error::from(set_error).to_string(),
+            "Set failed: forbidden: No"
+        );
+        assert!(
+            Error::from(std::io::Error::from(std::io::ErrorKind::NotFound))
+                .to_string()
+                .starts_with("I/O error: ")
+        );
+        #[cfg(feature = "websockets")]
+        assert!(
+            Error::from(tokio_tungstenite::tungstenite::Error::ConnectionClosed)
+                .to_string()
+                .starts_with("WebSockets error: ")
+        );
+    }
+}
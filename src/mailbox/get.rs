@@ -10,7 +10,7 @@
  */
 
 use super::{Mailbox, MailboxRights, Role};
-use crate::{core::get::GetObject, principal::ACL, Get, Set};
+use crate::{core::get::GetObject, principal::ACL, Error, Get, Set};
 use ahash::AHashMap;
 
 impl Mailbox<Get> {
@@ -62,6 +62,23 @@ impl Mailbox<Get> {
         self.my_rights.as_ref()
     }
 
+    /// Fails with [`Error::Forbidden`] if `myRights` was fetched and does not
+    /// grant `right`.
+    pub(crate) fn require_right(
+        &self,
+        name: &str,
+        right: impl Fn(&MailboxRights) -> bool,
+    ) -> crate::Result<()> {
+        match &self.my_rights {
+            Some(rights) if !right(rights) => Err(Error::Forbidden(format!(
+                "{} not granted on mailbox {}",
+                name,
+                self.id().unwrap_or_default()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
     pub fn acl(&self) -> Option<&AHashMap<String, Vec<ACL>>> {
         self.acl.as_ref()
     }
@@ -49,6 +49,42 @@ impl Client {
             .created(&id)
     }
 
+    /// Creates a hierarchy of mailboxes in a single request, each one nested
+    /// in the previous one through its creation id and the first one in
+    /// `parent_id`. Returns the created mailboxes in the same order.
+    #[maybe_async::maybe_async]
+    pub async fn mailbox_create_path<T, U>(
+        &self,
+        names: T,
+        parent_id: Option<impl Into<String>>,
+    ) -> crate::Result<Vec<Mailbox>>
+    where
+        T: IntoIterator<Item = U>,
+        U: Into<String>,
+    {
+        let mut request = self.build();
+        let set_request = request.set_mailbox();
+        let mut parent_id = parent_id.map(|id| id.into());
+        let mut create_ids = Vec::new();
+        for name in names {
+            let mailbox = set_request.create().name(name);
+            match &parent_id {
+                Some(id) if create_ids.is_empty() => mailbox.parent_id(Some(id)),
+                Some(id) => mailbox.parent_id_ref(id),
+                None => mailbox.parent_id(None::<String>),
+            };
+            let create_id = mailbox.create_id().unwrap();
+            parent_id = Some(create_id.clone());
+            create_ids.push(create_id);
+        }
+        if create_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut response = request.send_single::<MailboxSetResponse>().await?;
+        create_ids.iter().map(|id| response.created(id)).collect()
+    }
+
     #[maybe_async::maybe_async]
     pub async fn mailbox_rename(
         &self,
@@ -271,3 +307,43 @@ impl Request<'_> {
         self.send_single().await
     }
 }
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::test_server::{block_on, TestResponse, TestServer};
+
+    #[test]
+    fn mailbox_create_path() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let create = &request["methodCalls"][0][1]["create"];
+            assert_eq!(create["c0"]["parentId"], "inbox");
+            assert_eq!(create["c1"]["parentId"], "#c0");
+            TestResponse::json(
+                200,
+                serde_json::json!({
+                    "sessionState": "s1",
+                    "methodResponses": [["Mailbox/set", {
+                        "accountId": "a",
+                        "newState": "m2",
+                        "created": {"c0": {"id": "mb1"}, "c1": {"id": "mb2"}}
+                    }, "s0"]]
+                })
+                .to_string(),
+            )
+        });
+
+        let ids = block_on(async {
+            let client = server.connect().await;
+            client
+                .mailbox_create_path(["Projects", "2024"], Some("inbox"))
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|mailbox| mailbox.id().unwrap().to_string())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(ids, ["mb1", "mb2"]);
+        assert_eq!(server.requests().len(), 1);
+    }
+}
@@ -9,7 +9,10 @@
  * except according to those terms.
  */
 
-use crate::{core::get::GetObject, Get, Set, TypeState};
+use crate::{
+    core::{base64, get::GetObject},
+    Get, Set, TypeState,
+};
 
 use super::{Keys, PushSubscription};
 
@@ -49,11 +52,11 @@ impl PushSubscription<Get> {
 
 impl Keys {
     pub fn p256dh(&self) -> Option<Vec<u8>> {
-        base64::decode_config(&self.p256dh, base64::URL_SAFE).ok()
+        base64::decode(&self.p256dh)
     }
 
     pub fn auth(&self) -> Option<Vec<u8>> {
-        base64::decode_config(&self.auth, base64::URL_SAFE).ok()
+        base64::decode(&self.auth)
     }
 }
 
@@ -173,6 +173,20 @@ impl Client {
             .await?
             .unwrap_error()
     }
+
+    /// Uploads and validates a Sieve script without storing it. If the script
+    /// is invalid, [`Error::SieveParse`](crate::Error::SieveParse) is returned
+    /// with the position of the error when the server reports one.
+    #[maybe_async::maybe_async]
+    pub async fn validate_sieve(&self, script: impl Into<Vec<u8>>) -> crate::Result<()> {
+        let blob_id = self.upload(None, script.into(), None).await?.take_blob_id();
+        let mut request = self.build();
+        request.validate_sieve_script(blob_id);
+        request
+            .send_single::<SieveScriptValidateResponse>()
+            .await?
+            .unwrap_parse_error()
+    }
 }
 
 impl Request<'_> {
@@ -235,3 +249,72 @@ impl Request<'_> {
         self.send_single().await
     }
 }
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::{
+        test_server::{block_on, TestResponse, TestServer},
+        Error,
+    };
+
+    #[test]
+    fn validate_sieve() {
+        let server = TestServer::start(|request| {
+            if request.path.starts_with("/upload/") {
+                let blob_id = if request.body.starts_with(b"require") {
+                    "valid"
+                } else {
+                    "invalid"
+                };
+                return TestResponse::json(
+                    200,
+                    serde_json::json!({
+                        "accountId": "a",
+                        "blobId": blob_id,
+                        "type": "application/octet-stream",
+                        "size": request.body.len(),
+                    })
+                    .to_string(),
+                );
+            }
+
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let error = if request["methodCalls"][0][1]["blobId"] == "valid" {
+                serde_json::Value::Null
+            } else {
+                serde_json::json!({
+                    "type": "invalidScript",
+                    "description": "line 2, column 5: unknown command 'frobnicate'"
+                })
+            };
+            TestResponse::json(
+                200,
+                serde_json::json!({
+                    "sessionState": "s1",
+                    "methodResponses": [["SieveScript/validate", {
+                        "accountId": "a",
+                        "error": error,
+                    }, "s0"]]
+                })
+                .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            client
+                .validate_sieve(r#"require "fileinto"; fileinto "Spam";"#)
+                .await
+                .unwrap();
+
+            match client.validate_sieve("keep;\n    frobnicate;").await {
+                Err(Error::SieveParse(err)) => {
+                    assert_eq!(err.line(), Some(2));
+                    assert_eq!(err.column(), Some(5));
+                    assert_eq!(err.message(), "unknown command 'frobnicate'");
+                }
+                result => panic!("Unexpected result: {:?}", result),
+            }
+        });
+    }
+}
@@ -1,11 +1,16 @@
+use std::fmt::Display;
+
 use serde::{Deserialize, Serialize};
 
-use crate::core::{set::SetError, RequestParams};
+use crate::core::{
+    set::{SetError, SetErrorType},
+    AccountId, RequestParams,
+};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct SieveScriptValidateRequest {
     #[serde(rename = "accountId")]
-    account_id: String,
+    account_id: AccountId,
 
     #[serde(rename = "blobId")]
     blob_id: String,
@@ -20,6 +25,15 @@ pub struct SieveScriptValidateResponse {
     error: Option<SetError<String>>,
 }
 
+/// A Sieve script error reported by the server, with the position of the
+/// error when the server's description includes one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SieveParseError {
+    line: Option<usize>,
+    column: Option<usize>,
+    message: String,
+}
+
 impl SieveScriptValidateRequest {
     pub fn new(params: RequestParams, blob_id: impl Into<String>) -> Self {
         SieveScriptValidateRequest {
@@ -36,4 +50,107 @@ impl SieveScriptValidateResponse {
             None => Ok(()),
         }
     }
+
+    /// Same as [`SieveScriptValidateResponse::unwrap_error`], returning
+    /// [`crate::Error::SieveParse`] when the script is invalid.
+    pub fn unwrap_parse_error(self) -> crate::Result<()> {
+        match self.error {
+            Some(err) if err.error() == &SetErrorType::InvalidScript => {
+                Err(crate::Error::SieveParse(SieveParseError::parse(
+                    err.description().unwrap_or_default(),
+                )))
+            }
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl SieveParseError {
+    /// Parses descriptions such as `line 3, column 7: unknown command` or
+    /// `3:7: unknown command`.
+    pub fn parse(description: &str) -> Self {
+        let mut line = None;
+        let mut column = None;
+        let lowercase = description.to_ascii_lowercase();
+        for (name, value) in [("line", &mut line), ("column", &mut column)] {
+            if let Some(pos) = lowercase.find(name) {
+                *value = leading_number(lowercase[pos + name.len()..].trim_start());
+            }
+        }
+
+        let mut message = description;
+        if line.is_none() {
+            let mut parts = description.splitn(3, ':');
+            if let (Some(l), Some(c), Some(rest)) = (parts.next(), parts.next(), parts.next()) {
+                if let (Ok(l), Ok(c)) = (l.trim().parse(), c.trim().parse()) {
+                    line = Some(l);
+                    column = Some(c);
+                    message = rest;
+                }
+            }
+        } else if let Some((_, rest)) = description.split_once(':') {
+            message = rest;
+        }
+
+        SieveParseError {
+            line,
+            column,
+            message: message.trim().to_string(),
+        }
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for SieveParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {}, column {}: {}", line, column, self.message)
+            }
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+fn leading_number(value: &str) -> Option<usize> {
+    let end = value
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SieveParseError;
+
+    #[test]
+    fn parse_error() {
+        let err = SieveParseError::parse("Line 3, column 7: missing semicolon");
+        assert_eq!((err.line(), err.column()), (Some(3), Some(7)));
+        assert_eq!(err.message(), "missing semicolon");
+
+        let err = SieveParseError::parse("12:1: unexpected end of script");
+        assert_eq!((err.line(), err.column()), (Some(12), Some(1)));
+        assert_eq!(
+            err.to_string(),
+            "line 12, column 1: unexpected end of script"
+        );
+
+        let err = SieveParseError::parse("Script is too long");
+        assert_eq!((err.line(), err.column()), (None, None));
+        assert_eq!(err.message(), "Script is too long");
+    }
 }
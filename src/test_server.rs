@@ -0,0 +1,327 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+//! Minimal HTTP/1.1 server used by the unit tests to mock a JMAP server.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use crate::client::{Client, Credentials};
+
+pub(crate) struct TestServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<TestRequest>>>,
+    session_requests: Arc<Mutex<Vec<TestRequest>>>,
+    session: Arc<Mutex<serde_json::Value>>,
+    aborted: Arc<Mutex<mpsc::Receiver<()>>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TestRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+pub(crate) struct TestResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    chunks: Vec<Vec<u8>>,
+    chunk_delay: Duration,
+}
+
+impl TestServer {
+    /// Starts a server that answers `/.well-known/jmap` with a session
+    /// pointing back to itself and passes any other request to `handler`.
+    /// The session is served with its state as the ETag, and requests carrying
+    /// a matching `If-None-Match` receive a `304 Not Modified`.
+    pub fn start(
+        handler: impl Fn(&TestRequest) -> TestResponse + Send + Sync + 'static,
+    ) -> TestServer {
+        TestServer::start_with_session(|_| (), handler)
+    }
+
+    /// Same as [`TestServer::start`], allowing the session to be modified first.
+    pub fn start_with_session(
+        session_fn: impl FnOnce(&mut serde_json::Value),
+        handler: impl Fn(&TestRequest) -> TestResponse + Send + Sync + 'static,
+    ) -> TestServer {
+        TestServer::start_inner(listener(), session_fn, handler, false)
+    }
+
+    /// Same as [`TestServer::start`], accepting connections from `listener`.
+    pub fn start_on(
+        listener: TcpListener,
+        handler: impl Fn(&TestRequest) -> TestResponse + Send + Sync + 'static,
+    ) -> TestServer {
+        TestServer::start_inner(listener, |_| (), handler, false)
+    }
+
+    /// Same as [`TestServer::start_with_session`], serving each connection
+    /// from its own thread so that requests may overlap.
+    pub fn start_threaded(
+        session_fn: impl FnOnce(&mut serde_json::Value),
+        handler: impl Fn(&TestRequest) -> TestResponse + Send + Sync + 'static,
+    ) -> TestServer {
+        TestServer::start_inner(listener(), session_fn, handler, true)
+    }
+
+    fn start_inner(
+        listener: TcpListener,
+        session_fn: impl FnOnce(&mut serde_json::Value),
+        handler: impl Fn(&TestRequest) -> TestResponse + Send + Sync + 'static,
+        threaded: bool,
+    ) -> TestServer {
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let mut session = session(&url);
+        session_fn(&mut session);
+        let session = Arc::new(Mutex::new(session));
+        let session_requests = Arc::new(Mutex::new(Vec::new()));
+        let (aborted_tx, aborted) = mpsc::channel();
+
+        let requests_ = requests.clone();
+        let session_requests_ = session_requests.clone();
+        let session_ = session.clone();
+        let serve = Arc::new(move |mut stream: TcpStream| {
+            let request = match read_request(&mut stream) {
+                Some(request) => request,
+                None => return,
+            };
+            let response = if request.path == "/.well-known/jmap" {
+                session_requests_.lock().unwrap().push(request.clone());
+                let session = session_.lock().unwrap();
+                let etag = format!("\"{}\"", session["state"].as_str().unwrap_or_default());
+                if request.header("If-None-Match") == Some(etag.as_str()) {
+                    TestResponse::new(304, "")
+                } else {
+                    TestResponse::json(200, session.to_string()).header("ETag", etag)
+                }
+            } else {
+                requests_.lock().unwrap().push(request.clone());
+                handler(&request)
+            };
+            if response.write_to(&mut stream).is_err() {
+                let _ = aborted_tx.send(());
+            }
+        });
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                if threaded {
+                    let serve = serve.clone();
+                    std::thread::spawn(move || serve(stream));
+                } else {
+                    serve(stream);
+                }
+            }
+        });
+
+        TestServer {
+            url,
+            requests,
+            session_requests,
+            session,
+            aborted: Arc::new(Mutex::new(aborted)),
+        }
+    }
+
+    /// Modifies the session returned by later session requests.
+    pub fn update_session(&self, session_fn: impl FnOnce(&mut serde_json::Value)) {
+        session_fn(&mut self.session.lock().unwrap());
+    }
+
+    pub fn requests(&self) -> Vec<TestRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Waits up to `timeout` for a response that could not be written
+    /// because the client closed the connection.
+    pub async fn wait_aborted(&self, timeout: Duration) -> bool {
+        let aborted = self.aborted.clone();
+        tokio::task::spawn_blocking(move || aborted.lock().unwrap().recv_timeout(timeout).is_ok())
+            .await
+            .unwrap()
+    }
+
+    pub fn session_requests(&self) -> Vec<TestRequest> {
+        self.session_requests.lock().unwrap().clone()
+    }
+
+    pub async fn connect(&self) -> Client {
+        Client::new()
+            .credentials(Credentials::bearer("token"))
+            .connect(&self.url)
+            .await
+            .unwrap()
+    }
+}
+
+impl TestRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl TestResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> TestResponse {
+        TestResponse {
+            status,
+            headers: Vec::new(),
+            chunks: vec![body.into()],
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    pub fn json(status: u16, body: impl Into<Vec<u8>>) -> TestResponse {
+        TestResponse::new(status, body).header("Content-Type", "application/json")
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends the body in several writes, waiting `delay` before each one.
+    pub fn chunked(mut self, chunks: Vec<Vec<u8>>, delay: Duration) -> Self {
+        self.chunks = chunks;
+        self.chunk_delay = delay;
+        self
+    }
+
+    fn write_to(&self, stream: &mut impl Write) -> std::io::Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {} Status\r\nConnection: close\r\nContent-Length: {}\r\n",
+            self.status,
+            self.chunks.iter().map(|c| c.len()).sum::<usize>()
+        );
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.flush()?;
+
+        for chunk in &self.chunks {
+            if !self.chunk_delay.is_zero() {
+                std::thread::sleep(self.chunk_delay);
+            }
+            stream.write_all(chunk)?;
+            stream.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn listener() -> TcpListener {
+    TcpListener::bind("127.0.0.1:0").unwrap()
+}
+
+fn read_request(stream: &mut impl Read) -> Option<TestRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = TestRequest {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    if let Some(len) = request.header("Content-Length") {
+        let mut body = vec![0; len.parse().ok()?];
+        reader.read_exact(&mut body).ok()?;
+        request.body = body;
+    }
+
+    Some(request)
+}
+
+fn session(url: &str) -> serde_json::Value {
+    serde_json::json!({
+        "capabilities": {
+            "urn:ietf:params:jmap:core": {
+                "maxSizeUpload": 50000000,
+                "maxConcurrentUpload": 4,
+                "maxSizeRequest": 10000000,
+                "maxConcurrentRequests": 4,
+                "maxCallsInRequest": 16,
+                "maxObjectsInGet": 500,
+                "maxObjectsInSet": 500,
+                "collationAlgorithms": ["i;ascii-numeric", "i;ascii-casemap"]
+            },
+            "urn:ietf:params:jmap:mail": {
+                "maxMailboxesPerEmail": null,
+                "maxMailboxDepth": 10,
+                "maxSizeMailboxName": 255,
+                "maxSizeAttachmentsPerEmail": 50000000,
+                "emailQuerySortOptions": ["receivedAt", "size", "subject"],
+                "mayCreateTopLevelMailbox": true
+            }
+        },
+        "accounts": {
+            "a": {
+                "name": "jdoe@example.org",
+                "isPersonal": true,
+                "isReadOnly": false,
+                "accountCapabilities": {
+                    "urn:ietf:params:jmap:core": {},
+                    "urn:ietf:params:jmap:mail": {}
+                }
+            }
+        },
+        "primaryAccounts": {
+            "urn:ietf:params:jmap:core": "a",
+            "urn:ietf:params:jmap:mail": "a"
+        },
+        "username": "jdoe@example.org",
+        "apiUrl": format!("{}/api", url),
+        "downloadUrl": format!("{}/download/{{accountId}}/{{blobId}}/{{name}}?accept={{type}}", url),
+        "uploadUrl": format!("{}/upload/{{accountId}}/", url),
+        "eventSourceUrl": format!("{}/eventsource/?types={{types}}&closeafter={{closeafter}}&ping={{ping}}", url),
+        "state": "s1"
+    })
+}
+
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
@@ -16,8 +16,9 @@ use crate::{
         get::GetRequest,
         request::{Arguments, Request},
         response::ThreadGetResponse,
+        set::SetErrorType,
     },
-    Method,
+    email, Error, Method,
 };
 
 use super::Thread;
@@ -32,6 +33,95 @@ impl Client {
             .await
             .map(|mut r| r.take_list().pop())
     }
+
+    /// Returns the ids of the emails in the same thread as `email_id`, in the
+    /// order given by the server, or `None` if the email does not exist. The
+    /// email and its thread are fetched in a single request.
+    #[maybe_async::maybe_async]
+    pub async fn thread_of_email(&self, email_id: &str) -> crate::Result<Option<Vec<String>>> {
+        let mut request = self.build();
+        let thread_id_ref = request
+            .get_email()
+            .ids([email_id])
+            .properties([email::Property::ThreadId])
+            .result_reference(email::Property::ThreadId);
+        let email_call_id = request.last_call_id().unwrap().to_string();
+        request.get_thread().ids_ref(thread_id_ref);
+        let thread_call_id = request.last_call_id().unwrap().to_string();
+
+        let mut response = request.send().await?;
+        let email_found = response
+            .take_method_response_by_id(&email_call_id)
+            .ok_or_else(|| Error::from("Server returned no results"))?
+            .unwrap_get_email()?
+            .list()
+            .iter()
+            .any(|email| email.id() == Some(email_id));
+        if !email_found {
+            return Ok(None);
+        }
+
+        Ok(response
+            .take_method_response_by_id(&thread_call_id)
+            .ok_or_else(|| Error::from("Server returned no results"))?
+            .unwrap_get_thread()?
+            .take_list()
+            .pop()
+            .map(|mut thread| std::mem::take(&mut thread.email_ids)))
+    }
+
+    /// Adds the `$seen` keyword to all the emails in a thread. The updates
+    /// are split per the server's `maxObjectsInSet` limit and sent in as few
+    /// requests as possible. Emails that the server refuses to modify with a
+    /// `forbidden` error are skipped and their ids returned.
+    #[maybe_async::maybe_async]
+    pub async fn mark_thread_seen(&self, thread_id: &str) -> crate::Result<Vec<String>> {
+        let email_ids = self
+            .thread_get(thread_id)
+            .await?
+            .ok_or_else(|| Error::Internal(format!("Id {} not found.", thread_id)))?
+            .email_ids;
+        let max_objects_in_set = self
+            .session()
+            .core_capabilities()
+            .map(|capabilities| capabilities.max_objects_in_set())
+            .unwrap_or(email_ids.len())
+            .max(1);
+
+        let mut batch = self.batch();
+        let mut chunks = Vec::new();
+        for chunk in email_ids.chunks(max_objects_in_set) {
+            batch
+                .add(|request| {
+                    let set_request = request.set_email();
+                    for id in chunk {
+                        set_request.update(id).keyword("$seen", true);
+                    }
+                })
+                .await?;
+            chunks.push((chunk, batch.last_call_id().unwrap().to_string()));
+        }
+        batch.flush().await?;
+
+        let mut forbidden = Vec::new();
+        for (chunk, call_id) in chunks {
+            let mut response = batch
+                .take_response_by_id(&call_id)
+                .ok_or_else(|| Error::from("Server returned no results"))?
+                .unwrap_set_email()?;
+            for id in chunk {
+                match response.updated(id) {
+                    Ok(_) => (),
+                    Err(Error::Set(err)) if err.error() == &SetErrorType::Forbidden => {
+                        forbidden.push(id.clone());
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        Ok(forbidden)
+    }
 }
 
 impl Request<'_> {
@@ -61,3 +151,192 @@ impl Request<'_> {
         self.send_single().await
     }
 }
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::test_server::{block_on, TestResponse, TestServer};
+
+    #[test]
+    fn thread_of_email() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let (list, not_found) = if request["methodCalls"][0][1]["ids"][0] == "m2" {
+                (
+                    serde_json::json!([{"id": "m2", "threadId": "t1"}]),
+                    serde_json::json!([]),
+                )
+            } else {
+                (serde_json::json!([]), serde_json::json!(["missing"]))
+            };
+            let threads = if not_found.as_array().unwrap().is_empty() {
+                serde_json::json!([{"id": "t1", "emailIds": ["m1", "m2", "m3"]}])
+            } else {
+                serde_json::json!([])
+            };
+            TestResponse::json(
+                200,
+                serde_json::json!({
+                    "sessionState": "s1",
+                    "methodResponses": [
+                        ["Email/get", {
+                            "accountId": "a",
+                            "state": "e1",
+                            "list": list,
+                            "notFound": not_found
+                        }, "s0"],
+                        ["Thread/get", {
+                            "accountId": "a",
+                            "state": "t1",
+                            "list": threads,
+                            "notFound": []
+                        }, "s1"]
+                    ]
+                })
+                .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            assert_eq!(
+                client.thread_of_email("m2").await.unwrap().unwrap(),
+                ["m1", "m2", "m3"]
+            );
+            assert_eq!(client.thread_of_email("missing").await.unwrap(), None);
+        });
+
+        let request: serde_json::Value =
+            serde_json::from_slice(&server.requests()[0].body).unwrap();
+        assert_eq!(
+            request["methodCalls"][0][1]["properties"],
+            serde_json::json!(["threadId"])
+        );
+        assert_eq!(
+            request["methodCalls"][1],
+            serde_json::json!(["Thread/get", {
+                "accountId": "a",
+                "#ids": {"resultOf": "s0", "name": "Email/get", "path": "/list/*/threadId"}
+            }, "s1"])
+        );
+    }
+
+    #[test]
+    fn thread_of_email_reordered_responses() {
+        let server = TestServer::start(|_| {
+            TestResponse::json(
+                200,
+                serde_json::json!({
+                    "sessionState": "s1",
+                    "methodResponses": [
+                        ["Thread/get", {
+                            "accountId": "a",
+                            "state": "t1",
+                            "list": [{"id": "t1", "emailIds": ["m1", "m2"]}],
+                            "notFound": []
+                        }, "s1"],
+                        ["Email/get", {
+                            "accountId": "a",
+                            "state": "e1",
+                            "list": [{"id": "m2", "threadId": "t1"}],
+                            "notFound": []
+                        }, "s0"]
+                    ]
+                })
+                .to_string(),
+            )
+        });
+
+        block_on(async {
+            let client = server.connect().await;
+            assert_eq!(
+                client.thread_of_email("m2").await.unwrap().unwrap(),
+                ["m1", "m2"]
+            );
+        });
+    }
+
+    #[test]
+    fn mark_thread_seen() {
+        let server = TestServer::start(|request| {
+            let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let response = if request["methodCalls"][0][0] == "Thread/get" {
+                serde_json::json!(["Thread/get", {
+                    "accountId": "a",
+                    "state": "t1",
+                    "list": [{"id": "t1", "emailIds": ["m1", "m2"]}],
+                    "notFound": []
+                }, "s0"])
+            } else {
+                serde_json::json!(["Email/set", {
+                    "accountId": "a",
+                    "newState": "e2",
+                    "updated": {"m1": null},
+                    "notUpdated": {"m2": {"type": "forbidden"}}
+                }, "s0"])
+            };
+            TestResponse::json(
+                200,
+                serde_json::json!({"sessionState": "s1", "methodResponses": [response]})
+                    .to_string(),
+            )
+        });
+
+        let forbidden =
+            block_on(async { server.connect().await.mark_thread_seen("t1").await.unwrap() });
+        assert_eq!(forbidden, ["m2"]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let request: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(
+            request["methodCalls"][0][1]["update"],
+            serde_json::json!({
+                "m1": {"keywords/$seen": true},
+                "m2": {"keywords/$seen": true}
+            })
+        );
+    }
+
+    #[test]
+    fn mark_thread_seen_reordered_responses() {
+        let server = TestServer::start_with_session(
+            |session| {
+                session["capabilities"]["urn:ietf:params:jmap:core"]["maxObjectsInSet"] = 1.into()
+            },
+            |request| {
+                let request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let responses = if request["methodCalls"][0][0] == "Thread/get" {
+                    serde_json::json!([["Thread/get", {
+                        "accountId": "a",
+                        "state": "t1",
+                        "list": [{"id": "t1", "emailIds": ["m1", "m2"]}],
+                        "notFound": []
+                    }, "s0"]])
+                } else {
+                    serde_json::json!([
+                        ["Email/set", {
+                            "accountId": "a",
+                            "newState": "e3",
+                            "notUpdated": {"m2": {"type": "forbidden"}}
+                        }, "s1"],
+                        ["Email/set", {
+                            "accountId": "a",
+                            "newState": "e2",
+                            "updated": {"m1": null}
+                        }, "s0"]
+                    ])
+                };
+                TestResponse::json(
+                    200,
+                    serde_json::json!({"sessionState": "s1", "methodResponses": responses})
+                        .to_string(),
+                )
+            },
+        );
+
+        let forbidden =
+            block_on(async { server.connect().await.mark_thread_seen("t1").await.unwrap() });
+        assert_eq!(forbidden, ["m2"]);
+        assert_eq!(server.requests().len(), 2);
+    }
+}
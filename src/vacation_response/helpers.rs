@@ -15,7 +15,7 @@ use crate::{
         get::GetRequest,
         request::{Arguments, Request},
         response::{VacationResponseGetResponse, VacationResponseSetResponse},
-        set::{SetObject, SetRequest},
+        set::{SetObject, SetRequest, SINGLETON_ID},
     },
     Method, Set, URI,
 };
@@ -57,7 +57,8 @@ impl Client {
         let mut request = self.build();
         request
             .set_vacation_response()
-            .update("singleton")
+            .singleton()
+            .update()
             .is_enabled(true)
             .subject(Some(subject))
             .text_body(text_body)
@@ -66,7 +67,7 @@ impl Client {
         request
             .send_single::<VacationResponseSetResponse>()
             .await?
-            .updated("singleton")
+            .updated(SINGLETON_ID)
     }
 
     #[maybe_async::maybe_async]
@@ -74,13 +75,14 @@ impl Client {
         let mut request = self.build();
         request
             .set_vacation_response()
-            .update("singleton")
+            .singleton()
+            .update()
             .is_enabled(false);
 
         request
             .send_single::<VacationResponseSetResponse>()
             .await?
-            .updated("singleton")
+            .updated(SINGLETON_ID)
     }
 
     #[maybe_async::maybe_async]
@@ -92,7 +94,8 @@ impl Client {
         let mut request = self.build();
         request
             .set_vacation_response()
-            .update("singleton")
+            .singleton()
+            .update()
             .is_enabled(true)
             .from_date(from_date)
             .to_date(to_date);
@@ -100,7 +103,7 @@ impl Client {
         request
             .send_single::<VacationResponseSetResponse>()
             .await?
-            .updated("singleton")
+            .updated(SINGLETON_ID)
     }
 
     #[maybe_async::maybe_async]
@@ -109,7 +112,7 @@ impl Client {
         properties: Option<impl IntoIterator<Item = Property>>,
     ) -> crate::Result<Option<VacationResponse>> {
         let mut request = self.build();
-        let get_request = request.get_vacation_response().ids(["singleton"]);
+        let get_request = request.get_vacation_response().ids([SINGLETON_ID]);
         if let Some(properties) = properties {
             get_request.properties(properties.into_iter());
         }
@@ -122,11 +125,11 @@ impl Client {
     #[maybe_async::maybe_async]
     pub async fn vacation_response_destroy(&self) -> crate::Result<()> {
         let mut request = self.build();
-        request.set_vacation_response().destroy(["singleton"]);
+        request.set_vacation_response().destroy([SINGLETON_ID]);
         request
             .send_single::<VacationResponseSetResponse>()
             .await?
-            .destroyed("singleton")
+            .destroyed(SINGLETON_ID)
     }
 }
 
@@ -159,3 +162,34 @@ impl Request<'_> {
         self.send_single().await
     }
 }
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use crate::{
+        core::set::SetErrorType,
+        test_server::{block_on, TestResponse, TestServer},
+        Error,
+    };
+
+    #[test]
+    fn singleton_create_rejected() {
+        let server = TestServer::start(|_| TestResponse::new(500, ""));
+
+        block_on(async {
+            let client = server.connect().await;
+            let mut request = client.build();
+            let mut set = request.set_vacation_response().singleton();
+            match set.create() {
+                Err(Error::Set(err)) => assert_eq!(err.error(), &SetErrorType::Singleton),
+                other => panic!("unexpected result {:?}", other.map(|_| ())),
+            }
+            set.update().is_enabled(false);
+
+            let request = serde_json::to_value(&request).unwrap();
+            let call = &request["methodCalls"][0][1];
+            assert_eq!(call["update"]["singleton"]["isEnabled"], false);
+            assert!(call.get("create").is_none());
+        });
+        assert!(server.requests().is_empty());
+    }
+}